@@ -0,0 +1,300 @@
+use std::{
+    io::Write,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::escape_json,
+    header_item::HeaderItem,
+    http_date::HttpDate,
+    middleware::{Middleware, Next},
+    request::ServerRequest,
+    response::Response,
+};
+
+/// How each request is written to the access log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// The Common Log Format, such as
+    /// `127.0.0.1 - zak [06/Nov/1994:08:49:37 +0000] "GET /index.html HTTP/1.1" 200 2326`.
+    Common,
+    /// The Common Log Format followed by the quoted `Referer` and `User-Agent` headers.
+    #[default]
+    Combined,
+    /// One JSON object per line, which also has the time taken to respond in milliseconds.
+    Json,
+}
+
+/// The details of one request and its response.
+#[derive(Debug)]
+struct Entry {
+    time: HttpDate,
+    peer: String,
+    user: Option<String>,
+    request_line: String,
+    method: String,
+    uri: String,
+    version: String,
+    status: u16,
+    bytes: usize,
+    latency: Duration,
+    referer: Option<String>,
+    user_agent: Option<String>,
+}
+
+/// Middleware writing a line to `sink` for every request, after its response is ready.
+///
+/// The user is the username of `Basic` credentials, whether or not they were accepted. Lines
+/// are written whole and flushed straight away, and errors writing them are ignored so that
+/// logging never fails a request. Add it first with `Server::wrap` so that it also logs the
+/// responses of other middleware, such as rejected logins.
+pub struct AccessLog<W: Write + Send + 'static> {
+    sink: Mutex<W>,
+    format: LogFormat,
+}
+
+impl<W: Write + Send + 'static> std::fmt::Debug for AccessLog<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccessLog")
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl<W: Write + Send + 'static> AccessLog<W> {
+    pub fn new(sink: W) -> Self {
+        Self {
+            sink: Mutex::new(sink),
+            format: LogFormat::default(),
+        }
+    }
+
+    /// Defaults to `LogFormat::Combined`.
+    pub fn format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn sink(&self) -> MutexGuard<'_, W> {
+        self.sink.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn line(&self, entry: &Entry) -> String {
+        let quoted = |value: &Option<String>| match value {
+            Some(value) => format!("\"{}\"", escape_log(value, false)),
+            None => "\"-\"".to_owned(),
+        };
+
+        let common = || {
+            format!(
+                "{} - {} [{}] \"{}\" {} {}",
+                entry.peer,
+                entry
+                    .user
+                    .as_deref()
+                    .map_or_else(|| "-".to_owned(), |u| escape_log(u, true)),
+                entry.time.to_common_log(),
+                escape_log(&entry.request_line, false),
+                entry.status,
+                match entry.bytes {
+                    0 => "-".to_owned(),
+                    bytes => bytes.to_string(),
+                }
+            )
+        };
+
+        match self.format {
+            LogFormat::Common => common(),
+            LogFormat::Combined => format!(
+                "{} {} {}",
+                common(),
+                quoted(&entry.referer),
+                quoted(&entry.user_agent)
+            ),
+            LogFormat::Json => {
+                let json = |value: &Option<String>| match value {
+                    Some(value) => format!("\"{}\"", escape_json(value)),
+                    None => "null".to_owned(),
+                };
+
+                format!(
+                    concat!(
+                        r#"{{"time":"{}","peer":"{}","user":{},"method":"{}","uri":"{}","#,
+                        r#""version":"{}","status":{},"bytes":{},"latency_ms":{:.3},"#,
+                        r#""referer":{},"user_agent":{}}}"#
+                    ),
+                    entry.time.to_rfc3339(),
+                    escape_json(&entry.peer),
+                    json(&entry.user),
+                    escape_json(&entry.method),
+                    escape_json(&entry.uri),
+                    escape_json(&entry.version),
+                    entry.status,
+                    entry.bytes,
+                    entry.latency.as_secs_f64() * 1000.0,
+                    json(&entry.referer),
+                    json(&entry.user_agent)
+                )
+            }
+        }
+    }
+}
+
+/// Escapes quotes, backslashes and bytes outside printable ASCII as Apache does, and spaces too
+/// in fields that are not quoted, so that every line can be split back into its fields.
+fn escape_log(value: &str, escape_spaces: bool) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for b in value.bytes() {
+        match b {
+            b'"' => escaped.push_str("\\\""),
+            b'\\' => escaped.push_str("\\\\"),
+            b' ' if escape_spaces => escaped.push_str("\\x20"),
+            b if b.is_ascii_graphic() || b == b' ' => escaped.push(b as char),
+            b => escaped.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+
+    escaped
+}
+
+impl<W: Write + Send + 'static> Middleware for AccessLog<W> {
+    fn handle(&self, req: ServerRequest, next: Next<'_>) -> Response {
+        let start = Instant::now();
+
+        let header = &req.request.header;
+        let header_map = header.header_map();
+
+        let version = format!("HTTP/{}", header.version);
+
+        let mut entry = Entry {
+            time: HttpDate::now(),
+            peer: req.peer_address.ip().to_string(),
+            user: req
+                .authorization()
+                .and_then(|a| a.basic())
+                .map(|b| b.username().to_owned()),
+            request_line: format!("{} {} {}", header.method, header.uri, version),
+            method: header.method.to_string(),
+            uri: header.uri.clone(),
+            version,
+            status: 0,
+            bytes: 0,
+            latency: Duration::ZERO,
+            referer: header_map.get_by_str_key("referer").map(str::to_owned),
+            user_agent: header_map.get_by_str_key("user-agent").map(str::to_owned),
+        };
+
+        let res = next.run(req);
+
+        entry.status = res.header.status_code;
+        entry.bytes = res.body.contents.len();
+        entry.latency = start.elapsed();
+
+        let mut line = self.line(&entry);
+        line.push('\n');
+
+        let mut sink = self.sink();
+
+        let _ = sink.write_all(line.as_bytes()).and_then(|_| sink.flush());
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use crate::{
+        http_status::HttpStatus,
+        middleware::{Middleware, Next},
+        request::{RequestBuilder, ServerRequest},
+        response::{Response, ResponseBuilder},
+        route::RouteKey,
+    };
+
+    use super::{AccessLog, LogFormat};
+
+    fn log(format: LogFormat, req: RequestBuilder) -> String {
+        let access_log = AccessLog::new(Vec::new()).format(format);
+
+        let endpoint = |req: ServerRequest| -> Response {
+            match req.request.header.path() {
+                "/missing" => ResponseBuilder::new().status(HttpStatus::NotFound).build(),
+                _ => ResponseBuilder::new().body("Hello").build(),
+            }
+        };
+
+        let middleware: Vec<Box<dyn Middleware>> = Vec::new();
+
+        let req = ServerRequest::new(
+            RouteKey("/".to_owned()),
+            req.build(),
+            SocketAddr::from(([10, 0, 0, 1], 4000)),
+        );
+
+        access_log.handle(req, Next::new(&middleware, &endpoint));
+
+        let line = String::from_utf8(access_log.sink().clone()).unwrap();
+
+        assert!(line.ends_with('\n'));
+
+        line.trim_end().to_owned()
+    }
+
+    /// Removes the time, which changes between runs.
+    fn without_time(line: &str) -> String {
+        let start = line.find('[').unwrap();
+        let end = line.find(']').unwrap();
+
+        format!("{}{}", &line[..start], &line[end + 2..])
+    }
+
+    #[test]
+    fn common_and_combined() {
+        let line = log(LogFormat::Common, RequestBuilder::new().uri("/missing"));
+        assert_eq!(
+            without_time(&line),
+            r#"10.0.0.1 - - "GET /missing HTTP/1.1" 404 -"#
+        );
+
+        let req = RequestBuilder::new()
+            .uri("/hello?name=\"zak\"")
+            .basic_auth("zak smith", "secret")
+            .unwrap()
+            .insert_header_key_val("User-Agent", "curl/8.0")
+            .unwrap();
+
+        let line = log(LogFormat::Combined, req);
+        assert_eq!(
+            without_time(&line),
+            r#"10.0.0.1 - zak\x20smith "GET /hello?name=\"zak\" HTTP/1.1" 200 5 "-" "curl/8.0""#
+        );
+        assert!(line.contains(" +0000] "));
+    }
+
+    #[test]
+    fn json_lines() {
+        let req = RequestBuilder::new()
+            .uri("/hello")
+            .insert_header_key_val("Referer", "https://example.com/")
+            .unwrap();
+
+        let line = log(LogFormat::Json, req);
+
+        let time_end = line.find("\",\"peer\"").unwrap();
+        assert!(line.starts_with(r#"{"time":""#) && line[..time_end].ends_with('Z'));
+
+        let latency = line.find(r#","latency_ms":"#).unwrap();
+        assert_eq!(
+            &line[time_end..latency],
+            concat!(
+                r#"","peer":"10.0.0.1","user":null,"method":"GET","uri":"/hello","#,
+                r#""version":"HTTP/1.1","status":200,"bytes":5"#
+            )
+        );
+        assert!(line.ends_with(r#","referer":"https://example.com/","user_agent":null}"#));
+    }
+}
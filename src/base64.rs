@@ -0,0 +1,98 @@
+use crate::{error::HttpInternalError, Result};
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes `data` as padded base64 with the standard alphabet from RFC 4648.
+pub fn encode<T: AsRef<[u8]>>(data: T) -> String {
+    let data = data.as_ref();
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Decodes padded base64 with the standard alphabet, failing on any other input.
+pub fn decode<T: AsRef<[u8]>>(encoded: T) -> Result<Vec<u8>> {
+    let encoded = encoded.as_ref();
+
+    let invalid = || HttpInternalError::new("Invalid base64.");
+
+    if !encoded.len().is_multiple_of(4) {
+        return Err(invalid());
+    }
+
+    let mut decoded = Vec::with_capacity(encoded.len() / 4 * 3);
+
+    let chunks = encoded.len() / 4;
+
+    for (index, chunk) in encoded.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|b| **b == b'=').count();
+
+        if padding > 2 || (padding > 0 && index + 1 != chunks) {
+            return Err(invalid());
+        }
+
+        let mut n = 0u32;
+
+        for (i, b) in chunk[..4 - padding].iter().enumerate() {
+            let value = ALPHABET.iter().position(|a| a == b).ok_or_else(invalid)?;
+
+            n |= (value as u32) << (18 - 6 * i);
+        }
+
+        let bytes = n.to_be_bytes();
+        let len = 3 - padding;
+
+        // Bits that do not fit in the decoded bytes must be zero, so each input has one encoding.
+        if bytes[1 + len..].iter().any(|b| *b != 0) {
+            return Err(invalid());
+        }
+
+        decoded.extend_from_slice(&bytes[1..1 + len]);
+    }
+
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode};
+
+    #[test]
+    fn encode_and_decode() {
+        // RFC 4648 test vectors.
+        for (plain, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(encode(plain), encoded);
+            assert_eq!(decode(encoded).unwrap(), plain.as_bytes());
+        }
+
+        assert_eq!(decode("+/+/").unwrap(), [0xfb, 0xff, 0xbf]);
+
+        for invalid in ["Zg", "Zg=", "Zh==", "Zg==Zg==", "Z===", "Zm9v-A==", "Zm 9"] {
+            assert!(decode(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
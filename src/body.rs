@@ -1,10 +1,21 @@
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Write};
 
 use crate::{error::HttpInternalError, Result};
 
-#[derive(Debug)]
+const STREAM_CHUNK_SIZE: usize = 16 * 1024;
+
 pub struct Body {
     pub contents: Vec<u8>,
+    stream: Option<Box<dyn Read + Send>>,
+}
+
+impl std::fmt::Debug for Body {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Body")
+            .field("contents", &self.contents)
+            .field("stream", &self.stream.is_some())
+            .finish()
+    }
 }
 
 impl std::default::Default for Body {
@@ -23,15 +34,34 @@ impl Body {
     pub fn new<T: AsRef<[u8]>>(bytes: T) -> Self {
         Self {
             contents: bytes.as_ref().to_vec(),
+            stream: None,
         }
     }
 
     pub fn empty() -> Self {
         Self {
             contents: Vec::new(),
+            stream: None,
         }
     }
 
+    /// A body read from `reader` while it is sent with chunked transfer coding, so that it never
+    /// has to be held in memory. `contents` stays empty for such a body.
+    pub fn stream<R: Read + Send + 'static>(reader: R) -> Self {
+        Self {
+            contents: Vec::new(),
+            stream: Some(Box::new(reader)),
+        }
+    }
+
+    pub fn is_stream(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    pub(crate) fn take_stream(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stream.take()
+    }
+
     pub fn from_fixed_length<R: Read>(reader: R, content_length: usize) -> Result<Self> {
         let buf_reader = BufReader::new(reader);
 
@@ -47,7 +77,10 @@ impl Body {
                 content_length, r
             )))
         } else {
-            Ok(Self { contents })
+            Ok(Self {
+                contents,
+                stream: None,
+            })
         }
     }
 
@@ -105,7 +138,7 @@ impl Body {
             }
         }
 
-        let contents_len = contents.len().try_into()?;
+        let contents_len: u64 = contents.len().try_into()?;
 
         if expected_length != contents_len {
             Err(HttpInternalError::new(format!(
@@ -113,16 +146,47 @@ impl Body {
                 expected_length, contents_len
             )))
         } else {
-            Ok(Self { contents })
+            Ok(Self {
+                contents,
+                stream: None,
+            })
         }
     }
 }
 
+/// Copies `reader` to `writer` with chunked transfer coding, ending with the last chunk.
+pub(crate) fn write_chunked<R: Read + ?Sized, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+) -> Result<()> {
+    let mut buf = vec![0; STREAM_CHUNK_SIZE];
+
+    loop {
+        let r = match reader.read(&mut buf) {
+            Ok(r) => r,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        if r == 0 {
+            break;
+        }
+
+        write!(writer, "{:x}\r\n", r)?;
+        writer.write_all(&buf[..r])?;
+        writer.write_all(b"\r\n")?;
+    }
+
+    writer.write_all(b"0\r\n\r\n")?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::io::Cursor;
 
-    use super::Body;
+    use super::{write_chunked, Body};
 
     #[test]
     fn from_fixed_length() {
@@ -146,4 +210,18 @@ mod tests {
             std::str::from_utf8(&body.contents).unwrap()
         );
     }
+
+    #[test]
+    fn write_chunked_round_trip() {
+        let contents = (0..40_000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+        let mut encoded = Vec::new();
+        write_chunked(&mut Cursor::new(&contents), &mut encoded).unwrap();
+
+        assert!(encoded.starts_with(b"4000\r\n"));
+        assert!(encoded.ends_with(b"\r\n0\r\n\r\n"));
+
+        let body = Body::from_chunked_encoding(Cursor::new(encoded)).unwrap();
+        assert_eq!(body.contents, contents);
+    }
 }
@@ -1,20 +1,65 @@
 use std::io::{BufReader, BufWriter};
 use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Mutex, MutexGuard};
 use std::time::Duration;
 
+#[cfg(feature = "compression")]
+use crate::compression::ContentCoding;
+use crate::cookie::CookieStore;
+use crate::header_item::HeaderItem;
+#[cfg(feature = "compression")]
+use crate::header_name::HeaderName;
+#[cfg(feature = "compression")]
+use crate::header_value::HeaderValue;
 use crate::http_item::HttpItem;
-use crate::method::Method;
-use crate::request::RequestBuilder;
+use crate::request::Request;
 use crate::response::Response;
-use crate::url::IntoUrl;
+use crate::typed_header::Host;
 use crate::Result;
 
-#[derive(Debug, Default)]
-pub struct Client {}
+#[derive(Debug)]
+pub struct Client {
+    cookie_store: Option<Mutex<CookieStore>>,
+    #[cfg(feature = "compression")]
+    max_decoded_size: usize,
+}
+
+#[cfg_attr(not(feature = "compression"), allow(clippy::derivable_impls))]
+impl std::default::Default for Client {
+    fn default() -> Self {
+        Self {
+            cookie_store: None,
+            #[cfg(feature = "compression")]
+            max_decoded_size: 64 * 1024 * 1024,
+        }
+    }
+}
 
 impl Client {
     pub fn new() -> Self {
-        Client {}
+        Client::default()
+    }
+
+    /// The largest body a compressed response may decode to before `send` fails. Defaults to
+    /// 64 MiB.
+    #[cfg(feature = "compression")]
+    pub fn max_decoded_size(mut self, max_decoded_size: usize) -> Self {
+        self.max_decoded_size = max_decoded_size;
+        self
+    }
+
+    /// Stores cookies set by responses in `store` and sends them with later requests to the
+    /// same host. The host is taken from the `Host` header, or the server address if missing.
+    pub fn cookie_store(mut self, store: CookieStore) -> Self {
+        self.cookie_store = Some(Mutex::new(store));
+        self
+    }
+
+    /// The cookies stored so far, if a cookie store is enabled.
+    pub fn cookies(&self) -> Option<MutexGuard<'_, CookieStore>> {
+        self.cookie_store
+            .as_ref()
+            .map(|s| s.lock().unwrap_or_else(|e| e.into_inner()))
     }
 
     // pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {}
@@ -31,11 +76,73 @@ impl Client {
     //     Ok(response)
     // }
 
-    // pub fn send(&self) -> Result<Response> {
-    //     let (mut read_buf, mut write_buf) = Self::setup_connection(address)?;
-    //
-    //     let
-    // }
+    /// Sends `request` to `address` and reads the response.
+    ///
+    /// With the `compression` feature, gzip and deflate are offered through `Accept-Encoding`
+    /// unless the request sets it, and compressed responses are decoded before being returned.
+    pub fn send<A: ToSocketAddrs>(&self, address: A, mut request: Request) -> Result<Response> {
+        let (mut read_buf, mut write_buf) = Self::setup_connection(address)?;
+
+        #[cfg(feature = "compression")]
+        Self::accept_encoding(&mut request);
+
+        let host = match request.header.header_map().typed_get::<Host>() {
+            Some(host) => host.host().to_owned(),
+            None => read_buf.get_ref().peer_addr()?.ip().to_string(),
+        };
+
+        if let Some(store) = self.cookies() {
+            store.add_to_request(&mut request, &host);
+        }
+
+        request.write_to(&mut write_buf)?;
+
+        let response = Response::from_stream(&mut read_buf)?;
+
+        if let Some(mut store) = self.cookies() {
+            store.store_response(&response, &host, request.header.path());
+        }
+
+        #[cfg(feature = "compression")]
+        let response = self.decode(response)?;
+
+        Ok(response)
+    }
+
+    #[cfg(feature = "compression")]
+    fn accept_encoding(request: &mut Request) {
+        let header_map = request.header.header_map_mut();
+
+        if header_map.get_by_str_key("accept-encoding").is_none() {
+            header_map.insert(
+                HeaderName::ACCEPT_ENCODING,
+                HeaderValue::from_static("gzip, deflate"),
+            );
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    fn decode(&self, mut response: Response) -> Result<Response> {
+        let coding = response
+            .header
+            .header_map()
+            .get_by_str_key_as::<ContentCoding>("content-encoding");
+
+        if let Some(coding) = coding.filter(|c| *c != ContentCoding::Identity) {
+            response.body.contents =
+                coding.decode(&response.body.contents, self.max_decoded_size)?;
+
+            let header_map = response.header.header_map_mut();
+
+            header_map.remove("Content-Encoding");
+            header_map.insert(
+                HeaderName::CONTENT_LENGTH,
+                response.body.contents.len().into(),
+            );
+        }
+
+        Ok(response)
+    }
 
     fn setup_connection<A: ToSocketAddrs>(
         address: A,
@@ -60,9 +167,136 @@ impl Client {
 mod tests {
     use crate::client::Client;
 
+    #[cfg(feature = "compression")]
+    #[test]
+    fn decompress_response() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+
+        use crate::compression::{ContentCoding, Level};
+        use crate::header_item::HeaderItem;
+        use crate::request::RequestBuilder;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let mut accept_encoding = None;
+
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+
+                    if line == "\r\n" {
+                        break;
+                    }
+
+                    if let Some(v) = line.strip_prefix("Accept-Encoding: ") {
+                        accept_encoding = Some(v.trim().to_owned());
+                    }
+                }
+
+                let body = ContentCoding::Gzip
+                    .encode(b"Hello compressed world", Level::default())
+                    .unwrap();
+
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+            }
+
+            accept_encoding
+        });
+
+        let res = Client::new()
+            .send(address, RequestBuilder::new().build())
+            .unwrap();
+
+        let too_large = Client::new()
+            .max_decoded_size(5)
+            .send(address, RequestBuilder::new().build());
+        assert!(too_large.is_err());
+
+        assert_eq!(server.join().unwrap().as_deref(), Some("gzip, deflate"));
+        assert_eq!(
+            res.header.header_map().get_by_str_key("content-encoding"),
+            None
+        );
+        assert_eq!(res.body.contents, b"Hello compressed world");
+    }
+
+    #[test]
+    fn replay_cookies() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+
+        use crate::cookie::CookieStore;
+        use crate::request::RequestBuilder;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let mut cookies = Vec::new();
+
+            for set_cookie in ["session=abc; Path=/; HttpOnly", "theme=dark"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut cookie = None;
+
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+
+                    if line == "\r\n" {
+                        break;
+                    }
+
+                    if let Some(v) = line.strip_prefix("Cookie: ") {
+                        cookie = Some(v.trim().to_owned());
+                    }
+                }
+
+                cookies.push(cookie);
+
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nSet-Cookie: {}\r\nContent-Length: 0\r\n\r\n",
+                    set_cookie
+                )
+                .unwrap();
+            }
+
+            cookies
+        });
+
+        let client = Client::new().cookie_store(CookieStore::new());
+
+        for uri in ["/login", "/account/settings"] {
+            client
+                .send(address, RequestBuilder::new().uri(uri).build())
+                .unwrap();
+        }
+
+        assert_eq!(
+            server.join().unwrap(),
+            [None, Some("session=abc".to_owned())]
+        );
+        assert_eq!(client.cookies().unwrap().len(), 2);
+    }
+
     #[test]
     fn test_get() {
-        let client = Client::new();
+        let _client = Client::new();
 
         // let res = client.get("127.0.0.1:1234").unwrap().text().unwrap();
         //
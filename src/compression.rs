@@ -0,0 +1,483 @@
+use std::{
+    io::{Read, Write},
+    str::FromStr,
+};
+
+use flate2::{
+    read::{self, DeflateDecoder, GzDecoder, ZlibDecoder},
+    write::{GzEncoder, ZlibEncoder},
+};
+
+use crate::{
+    body::Body,
+    conditional::EntityTag,
+    error::HttpInternalError,
+    header_item::HeaderItem,
+    header_name::HeaderName,
+    header_value::HeaderValue,
+    middleware::{Middleware, Next},
+    mime::Mime,
+    negotiation::QualityList,
+    request::ServerRequest,
+    response::Response,
+    Result,
+};
+
+pub use flate2::Compression as Level;
+
+/// A content coding for the `Accept-Encoding` and `Content-Encoding` headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentCoding {
+    Gzip,
+    Deflate,
+    Identity,
+}
+
+impl ContentCoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Deflate => "deflate",
+            ContentCoding::Identity => "identity",
+        }
+    }
+
+    /// Picks the coding the client prefers from an `Accept-Encoding` header, using the order of
+    /// `supported` to break ties. Falls back to `Identity` when nothing else is acceptable.
+    pub fn negotiate(accept_encoding: &str, supported: &[ContentCoding]) -> ContentCoding {
+        let supported = supported
+            .iter()
+            .filter(|c| **c != ContentCoding::Identity)
+            .map(|c| c.as_str())
+            .collect::<Vec<_>>();
+
+        QualityList::parse(accept_encoding)
+            .best_match(&supported, |range, coding| match range {
+                "*" => Some(0),
+                "x-gzip" if coding == "gzip" => Some(1),
+                _ => range.eq_ignore_ascii_case(coding).then_some(1),
+            })
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(ContentCoding::Identity)
+    }
+
+    /// Compresses `data` in a single pass.
+    pub fn encode(&self, data: &[u8], level: Level) -> Result<Vec<u8>> {
+        let encoded = match self {
+            ContentCoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), level);
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            ContentCoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), level);
+                encoder.write_all(data)?;
+                encoder.finish()?
+            }
+            ContentCoding::Identity => data.to_vec(),
+        };
+
+        Ok(encoded)
+    }
+
+    /// Decompresses `data`, failing if the result would be larger than `max_size` bytes so that
+    /// a small compressed body cannot fill memory. A `deflate` body is accepted both zlib
+    /// wrapped, as the specification requires, and raw, as some servers send it.
+    pub fn decode(&self, data: &[u8], max_size: usize) -> Result<Vec<u8>> {
+        let limit = max_size as u64 + 1;
+
+        let mut decoded = Vec::new();
+
+        match self {
+            ContentCoding::Gzip => {
+                GzDecoder::new(data).take(limit).read_to_end(&mut decoded)?;
+            }
+            ContentCoding::Deflate => {
+                if ZlibDecoder::new(data)
+                    .take(limit)
+                    .read_to_end(&mut decoded)
+                    .is_err()
+                {
+                    decoded.clear();
+                    DeflateDecoder::new(data)
+                        .take(limit)
+                        .read_to_end(&mut decoded)?;
+                }
+            }
+            ContentCoding::Identity => decoded.extend_from_slice(data),
+        }
+
+        if decoded.len() > max_size {
+            return Err(HttpInternalError::new(format!(
+                "Decoded body is larger than {} bytes.",
+                max_size
+            )));
+        }
+
+        Ok(decoded)
+    }
+}
+
+impl std::fmt::Display for ContentCoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ContentCoding {
+    type Err = HttpInternalError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Ok(ContentCoding::Gzip),
+            "deflate" => Ok(ContentCoding::Deflate),
+            "identity" => Ok(ContentCoding::Identity),
+            _ => Err(HttpInternalError::new(format!(
+                "Unsupported content coding '{}'.",
+                s
+            ))),
+        }
+    }
+}
+
+/// Compresses a body as it is read, for streamed bodies sent with chunked transfer coding.
+pub enum Encoder<R: Read> {
+    Gzip(read::GzEncoder<R>),
+    Deflate(read::ZlibEncoder<R>),
+    Identity(R),
+}
+
+impl<R: Read> std::fmt::Debug for Encoder<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let coding = match self {
+            Encoder::Gzip(_) => ContentCoding::Gzip,
+            Encoder::Deflate(_) => ContentCoding::Deflate,
+            Encoder::Identity(_) => ContentCoding::Identity,
+        };
+
+        f.debug_tuple("Encoder").field(&coding).finish()
+    }
+}
+
+impl<R: Read> Encoder<R> {
+    pub fn new(coding: ContentCoding, reader: R, level: Level) -> Self {
+        match coding {
+            ContentCoding::Gzip => Encoder::Gzip(read::GzEncoder::new(reader, level)),
+            ContentCoding::Deflate => Encoder::Deflate(read::ZlibEncoder::new(reader, level)),
+            ContentCoding::Identity => Encoder::Identity(reader),
+        }
+    }
+}
+
+impl<R: Read> Read for Encoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Encoder::Gzip(e) => e.read(buf),
+            Encoder::Deflate(e) => e.read(buf),
+            Encoder::Identity(r) => r.read(buf),
+        }
+    }
+}
+
+/// Middleware compressing response bodies with the coding negotiated from `Accept-Encoding`.
+///
+/// Buffered bodies are compressed whole once the handler has returned them, and streamed bodies
+/// are compressed chunk by chunk as they are sent.
+///
+/// Only textual content types are compressed, and buffered bodies only when they are at least
+/// `min_size` bytes. Responses that are already encoded, partial, or marked
+/// `Cache-Control: no-transform` are left as they are.
+#[derive(Debug, Clone)]
+pub struct Compression {
+    min_size: usize,
+    level: Level,
+    codings: Vec<ContentCoding>,
+}
+
+impl std::default::Default for Compression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compression {
+    pub fn new() -> Self {
+        Self {
+            min_size: 1024,
+            level: Level::default(),
+            codings: vec![ContentCoding::Gzip, ContentCoding::Deflate],
+        }
+    }
+
+    /// Bodies smaller than this are sent uncompressed. Defaults to 1024 bytes.
+    pub fn min_size(mut self, min_size: usize) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// The codings offered to clients, in order of preference. Defaults to gzip then deflate.
+    pub fn codings<I: IntoIterator<Item = ContentCoding>>(mut self, codings: I) -> Self {
+        self.codings = codings.into_iter().collect();
+        self
+    }
+
+    fn is_eligible(&self, res: &Response) -> bool {
+        let header_map = res.header.header_map();
+
+        let is_compressible = header_map
+            .get_by_str_key_as::<Mime>("content-type")
+            .is_some_and(|c| is_compressible(&c));
+
+        (200..300).contains(&res.header.status_code)
+            && res.header.status_code != 204
+            && res.header.status_code != 206
+            && (res.body.is_stream() || res.body.contents.len() >= self.min_size)
+            && is_compressible
+            && header_map.get_by_str_key("content-encoding").is_none()
+            && header_map.get_by_str_key("content-range").is_none()
+            && !header_map
+                .get_by_str_key("cache-control")
+                .is_some_and(|c| c.to_ascii_lowercase().contains("no-transform"))
+    }
+}
+
+impl Middleware for Compression {
+    fn handle(&self, req: ServerRequest, next: Next<'_>) -> Response {
+        let coding = req
+            .request
+            .header
+            .header_map()
+            .get_by_str_key("accept-encoding")
+            .map(|a| ContentCoding::negotiate(a, &self.codings))
+            .unwrap_or(ContentCoding::Identity);
+
+        let mut res = next.run(req);
+
+        if !self.is_eligible(&res) {
+            return res;
+        }
+
+        res.header
+            .header_map_mut()
+            .add_vary(HeaderName::ACCEPT_ENCODING);
+
+        if coding == ContentCoding::Identity {
+            return res;
+        }
+
+        if let Some(stream) = res.body.take_stream() {
+            res.body = Body::stream(Encoder::new(coding, stream, self.level));
+        } else {
+            let compressed = match coding.encode(&res.body.contents, self.level) {
+                Ok(compressed) => compressed,
+                Err(_) => return res,
+            };
+
+            res.header
+                .header_map_mut()
+                .insert(HeaderName::CONTENT_LENGTH, compressed.len().into());
+
+            res.body.contents = compressed;
+        }
+
+        let header_map = res.header.header_map_mut();
+
+        header_map.insert(
+            HeaderName::CONTENT_ENCODING,
+            HeaderValue::from_static(coding.as_str()),
+        );
+
+        // The compressed bytes differ from the identity representation, so a strong validator
+        // no longer applies to them.
+        if let Some(etag) = header_map
+            .get_by_str_key_as::<EntityTag>("etag")
+            .filter(|e| !e.is_weak())
+        {
+            header_map.insert(HeaderName::ETAG, (&etag.to_weak()).into());
+        }
+
+        res
+    }
+}
+
+fn is_compressible(mime: &Mime) -> bool {
+    let essence = mime.essence().to_ascii_lowercase();
+
+    essence.starts_with("text/")
+        || matches!(
+            essence.as_str(),
+            "application/json"
+                | "application/javascript"
+                | "application/xml"
+                | "application/wasm"
+                | "application/x-www-form-urlencoded"
+                | "image/svg+xml"
+        )
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, net::SocketAddr};
+
+    use crate::{
+        body::Body,
+        header_item::HeaderItem,
+        http_item::HttpItem,
+        middleware::{Middleware, Next},
+        mime::Mime,
+        request::{RequestBuilder, ServerRequest},
+        response::{Response, ResponseBuilder},
+        route::RouteKey,
+    };
+
+    use super::{Compression, ContentCoding};
+
+    fn compress(accept_encoding: Option<&str>, res: fn() -> Response) -> Response {
+        let middleware: Vec<Box<dyn Middleware>> = vec![Box::new(Compression::new().min_size(16))];
+
+        let endpoint = move |_: ServerRequest| res();
+
+        let mut req = RequestBuilder::new();
+
+        if let Some(a) = accept_encoding {
+            req = req.insert_header_key_val("Accept-Encoding", a).unwrap();
+        }
+
+        let req = ServerRequest::new(
+            RouteKey("/".to_owned()),
+            req.build(),
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+        );
+
+        Next::new(&middleware, &endpoint).run(req)
+    }
+
+    fn json() -> Response {
+        ResponseBuilder::json_str(format!("[{}]", vec!["{\"a\":1}"; 64].join(",")))
+            .insert_header_key_val("ETag", "\"v1\"")
+            .unwrap()
+            .build()
+    }
+
+    #[test]
+    fn negotiate() {
+        use ContentCoding::*;
+
+        let supported = [Gzip, Deflate];
+
+        assert_eq!(
+            ContentCoding::negotiate("gzip, deflate, br", &supported),
+            Gzip
+        );
+        assert_eq!(
+            ContentCoding::negotiate("gzip;q=0.5, deflate", &supported),
+            Deflate
+        );
+        assert_eq!(ContentCoding::negotiate("br", &supported), Identity);
+        assert_eq!(ContentCoding::negotiate("*;q=0.1", &supported), Gzip);
+        assert_eq!(ContentCoding::negotiate("gzip;q=0, *", &supported), Deflate);
+        assert_eq!(ContentCoding::negotiate("", &supported), Identity);
+    }
+
+    #[test]
+    fn compress_responses() {
+        let res = compress(Some("gzip, deflate, br"), json);
+        let header_map = res.header.header_map();
+
+        assert_eq!(header_map.get_by_str_key("content-encoding"), Some("gzip"));
+        assert_eq!(header_map.get_by_str_key("vary"), Some("Accept-Encoding"));
+        assert_eq!(header_map.get_by_str_key("etag"), Some("W/\"v1\""));
+        assert_eq!(
+            header_map.get_by_str_key_as::<usize>("content-length"),
+            Some(res.body.contents.len())
+        );
+        assert_eq!(
+            ContentCoding::Gzip
+                .decode(&res.body.contents, 1024 * 1024)
+                .unwrap(),
+            json().body.contents
+        );
+        assert!(ContentCoding::Gzip
+            .decode(&res.body.contents, json().body.contents.len() - 1)
+            .is_err());
+
+        let res = compress(Some("deflate"), json);
+        assert_eq!(
+            ContentCoding::Deflate
+                .decode(&res.body.contents, 1024 * 1024)
+                .unwrap(),
+            json().body.contents
+        );
+
+        let res = compress(None, json);
+        assert_eq!(
+            res.header.header_map().get_by_str_key("content-encoding"),
+            None
+        );
+        assert_eq!(
+            res.header.header_map().get_by_str_key("vary"),
+            Some("Accept-Encoding")
+        );
+
+        let res = compress(Some("gzip"), || {
+            ResponseBuilder::octet_stream(vec![0; 64]).build()
+        });
+        assert_eq!(
+            res.header.header_map().get_by_str_key("content-encoding"),
+            None
+        );
+
+        let res = compress(Some("gzip"), || ResponseBuilder::text("tiny").build());
+        assert_eq!(
+            res.header.header_map().get_by_str_key("content-encoding"),
+            None
+        );
+    }
+
+    #[test]
+    fn compress_streams() {
+        let stream = || {
+            ResponseBuilder::new()
+                .content_type(Mime::APPLICATION_JSON)
+                .stream(Cursor::new(json().body.contents))
+                .build()
+        };
+
+        let mut res = compress(Some("gzip"), stream);
+        let header_map = res.header.header_map();
+
+        assert_eq!(header_map.get_by_str_key("content-encoding"), Some("gzip"));
+        assert_eq!(
+            header_map.get_by_str_key("transfer-encoding"),
+            Some("chunked")
+        );
+        assert_eq!(header_map.get_by_str_key("content-length"), None);
+
+        let mut written = Vec::new();
+        res.write_to(&mut written).unwrap();
+
+        let body_start = written.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let body = Body::from_chunked_encoding(&written[body_start..]).unwrap();
+
+        assert_eq!(
+            ContentCoding::Gzip
+                .decode(&body.contents, 1024 * 1024)
+                .unwrap(),
+            json().body.contents
+        );
+
+        let res = compress(None, stream);
+        assert_eq!(
+            res.header.header_map().get_by_str_key("content-encoding"),
+            None
+        );
+        assert!(res.body.is_stream());
+    }
+}
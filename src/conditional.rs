@@ -0,0 +1,486 @@
+use std::{borrow::Cow, fs::Metadata, str::FromStr, time::SystemTime};
+
+use crate::{
+    error::{HttpError, HttpInternalError},
+    header_item::HeaderItem,
+    header_map::HeaderMap,
+    header_name::HeaderName,
+    header_value::HeaderValue,
+    http_date::HttpDate,
+    http_status::HttpStatus,
+    method::Method,
+    response::{Response, ResponseBuilder},
+};
+
+/// Headers copied from a response to the `304 Not Modified` that replaces it.
+const NOT_MODIFIED_HEADERS: [HeaderName; 7] = [
+    HeaderName::CACHE_CONTROL,
+    HeaderName::CONTENT_LOCATION,
+    HeaderName::DATE,
+    HeaderName::ETAG,
+    HeaderName::EXPIRES,
+    HeaderName::LAST_MODIFIED,
+    HeaderName::VARY,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntityTag {
+    weak: bool,
+    tag: Cow<'static, str>,
+}
+
+impl EntityTag {
+    /// Fails if `tag` contains a `"`, a space, a control character or a non-ASCII character.
+    pub fn new<T: Into<String>>(tag: T, weak: bool) -> crate::Result<Self> {
+        let tag = tag.into();
+
+        if !is_valid_tag(tag.as_bytes()) {
+            return Err(HttpInternalError::new(format!(
+                "Invalid entity tag '{}'.",
+                tag.escape_debug()
+            )));
+        }
+
+        Ok(Self {
+            weak,
+            tag: Cow::Owned(tag),
+        })
+    }
+
+    /// # Panics
+    ///
+    /// Panics, at compile time in a `const`, if `tag` is not a valid entity tag.
+    pub const fn strong(tag: &'static str) -> Self {
+        Self::from_static(tag, false)
+    }
+
+    /// # Panics
+    ///
+    /// Panics, at compile time in a `const`, if `tag` is not a valid entity tag.
+    pub const fn weak(tag: &'static str) -> Self {
+        Self::from_static(tag, true)
+    }
+
+    const fn from_static(tag: &'static str, weak: bool) -> Self {
+        assert!(is_valid_tag(tag.as_bytes()), "Invalid entity tag.");
+
+        Self {
+            weak,
+            tag: Cow::Borrowed(tag),
+        }
+    }
+
+    /// For tags built by this crate from hex digits and `-`.
+    fn from_trusted(tag: String, weak: bool) -> Self {
+        debug_assert!(is_valid_tag(tag.as_bytes()), "Invalid entity tag.");
+
+        Self {
+            weak,
+            tag: Cow::Owned(tag),
+        }
+    }
+
+    /// A strong tag derived from a file's size, modification time to the nanosecond and, on
+    /// Unix, inode, so that a rewritten or replaced file gets a new tag and downloads can be
+    /// resumed with `If-Range`. `None` if the file has no modification time.
+    pub fn from_file_metadata(metadata: &Metadata) -> Option<Self> {
+        let modified = metadata
+            .modified()
+            .ok()?
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .ok()?;
+
+        let tag = format!(
+            "{:x}.{:x}-{:x}",
+            modified.as_secs(),
+            modified.subsec_nanos(),
+            metadata.len()
+        );
+
+        #[cfg(unix)]
+        let tag = {
+            use std::os::unix::fs::MetadataExt;
+
+            format!("{:x}-{}", metadata.ino(), tag)
+        };
+
+        Some(Self::from_trusted(tag, false))
+    }
+
+    /// A strong tag derived from a hash of `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        // 64-bit FNV-1a, stable across runs and platforms unlike `DefaultHasher`.
+        let hash = bytes.iter().fold(0xcbf29ce484222325_u64, |hash, b| {
+            (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+        });
+
+        Self::from_trusted(format!("{:x}-{:016x}", bytes.len(), hash), false)
+    }
+
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// The same tag marked weak, for when the representation changes in a way that keeps it
+    /// semantically equivalent.
+    pub fn to_weak(&self) -> Self {
+        Self {
+            weak: true,
+            tag: self.tag.clone(),
+        }
+    }
+
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn strong_eq(&self, other: &EntityTag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+const fn is_valid_tag(tag: &[u8]) -> bool {
+    let mut i = 0;
+
+    while i < tag.len() {
+        let b = tag[i];
+
+        if !(b == 0x21 || (b >= 0x23 && b <= 0x7e)) {
+            return false;
+        }
+
+        i += 1;
+    }
+
+    true
+}
+
+impl std::fmt::Display for EntityTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.weak {
+            write!(f, "W/\"{}\"", self.tag)
+        } else {
+            write!(f, "\"{}\"", self.tag)
+        }
+    }
+}
+
+impl From<&EntityTag> for HeaderValue {
+    fn from(etag: &EntityTag) -> Self {
+        HeaderValue::from_trusted(etag.to_string())
+    }
+}
+
+impl FromStr for EntityTag {
+    type Err = HttpInternalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        let (weak, quoted) = match s.strip_prefix("W/") {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+
+        quoted
+            .strip_prefix('"')
+            .and_then(|q| q.strip_suffix('"'))
+            .filter(|t| is_valid_tag(t.as_bytes()))
+            .map(|t| Self {
+                weak,
+                tag: Cow::Owned(t.to_owned()),
+            })
+            .ok_or_else(|| HttpInternalError::new(format!("Invalid entity tag '{}'.", s)))
+    }
+}
+
+/// The value of an `If-Match` or `If-None-Match` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TagList {
+    Any,
+    Tags(Vec<EntityTag>),
+}
+
+impl TagList {
+    fn parse(s: &str) -> Self {
+        if s.trim() == "*" {
+            TagList::Any
+        } else {
+            TagList::Tags(s.split(',').filter_map(|t| t.parse().ok()).collect())
+        }
+    }
+
+    fn matches(&self, etag: Option<&EntityTag>, weak: bool) -> bool {
+        match (self, etag) {
+            (TagList::Any, etag) => etag.is_some(),
+            (TagList::Tags(_), None) => false,
+            (TagList::Tags(tags), Some(etag)) => tags.iter().any(|t| {
+                if weak {
+                    t.weak_eq(etag)
+                } else {
+                    t.strong_eq(etag)
+                }
+            }),
+        }
+    }
+}
+
+/// The conditional headers of a request, evaluated as described in RFC 9110 section 13.2.2.
+///
+/// The server applies them on its own only to successful `GET` and `HEAD` responses. Handlers
+/// changing a resource, such as for `PUT` and `DELETE`, must call
+/// `ServerRequest::check_preconditions` with its current state before changing it, passing `None`
+/// for a resource that does not exist yet.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preconditions {
+    if_match: Option<TagList>,
+    if_none_match: Option<TagList>,
+    if_modified_since: Option<HttpDate>,
+    if_unmodified_since: Option<HttpDate>,
+}
+
+impl Preconditions {
+    pub fn from_headers(header_map: &HeaderMap) -> Self {
+        Self {
+            if_match: header_map.get_by_str_key("if-match").map(TagList::parse),
+            if_none_match: header_map
+                .get_by_str_key("if-none-match")
+                .map(TagList::parse),
+            if_modified_since: header_map.if_modified_since(),
+            if_unmodified_since: header_map.if_unmodified_since(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// The status to respond with instead of the selected representation, if any precondition
+    /// fails.
+    pub fn evaluate(
+        &self,
+        method: &Method,
+        etag: Option<&EntityTag>,
+        last_modified: Option<HttpDate>,
+    ) -> Option<HttpStatus> {
+        let is_get_or_head = matches!(method, Method::GET | Method::HEAD);
+
+        if let Some(if_match) = &self.if_match {
+            if !if_match.matches(etag, false) {
+                return Some(HttpStatus::PreconditionFailed);
+            }
+        } else if let (Some(since), Some(modified)) = (self.if_unmodified_since, last_modified) {
+            if modified > since {
+                return Some(HttpStatus::PreconditionFailed);
+            }
+        }
+
+        if let Some(if_none_match) = &self.if_none_match {
+            if if_none_match.matches(etag, true) {
+                return Some(if is_get_or_head {
+                    HttpStatus::NotModified
+                } else {
+                    HttpStatus::PreconditionFailed
+                });
+            }
+        } else if let (true, Some(since), Some(modified)) =
+            (is_get_or_head, self.if_modified_since, last_modified)
+        {
+            if modified <= since {
+                return Some(HttpStatus::NotModified);
+            }
+        }
+
+        None
+    }
+
+    /// Checks the preconditions against the current state of a resource before changing it,
+    /// failing with `412 Precondition Failed`.
+    pub fn check(
+        &self,
+        method: &Method,
+        etag: Option<&EntityTag>,
+        last_modified: Option<HttpDate>,
+    ) -> std::result::Result<(), HttpError> {
+        match self.evaluate(method, etag, last_modified) {
+            Some(status) => Err(HttpError::new(status.to_string(), status)),
+            None => Ok(()),
+        }
+    }
+
+    /// Replaces a successful `GET` or `HEAD` response with `304 Not Modified` or
+    /// `412 Precondition Failed` based on its `ETag` and `Last-Modified` headers.
+    pub fn apply(&self, method: &Method, res: Response) -> Response {
+        if self.is_empty()
+            || !matches!(method, Method::GET | Method::HEAD)
+            || !(200..300).contains(&res.header.status_code)
+        {
+            return res;
+        }
+
+        let header_map = res.header.header_map();
+
+        let etag = header_map.get_by_str_key_as::<EntityTag>("etag");
+        let last_modified = header_map.last_modified();
+
+        match self.evaluate(method, etag.as_ref(), last_modified) {
+            Some(HttpStatus::NotModified) => {
+                let mut builder = ResponseBuilder::new().status(HttpStatus::NotModified);
+
+                for (k, v) in header_map
+                    .iter()
+                    .filter(|(k, _)| NOT_MODIFIED_HEADERS.contains(k))
+                {
+                    builder = builder.append_header(k.clone(), v.clone());
+                }
+
+                builder.build()
+            }
+            Some(status) => ResponseBuilder::new().status(status).build(),
+            None => res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        header_item::HeaderItem, header_map::HeaderMap, header_name::HeaderName,
+        header_value::HeaderValue, http_date::HttpDate, http_status::HttpStatus, method::Method,
+        response::ResponseBuilder,
+    };
+
+    use super::{EntityTag, Preconditions};
+
+    fn preconditions(headers: &[(&str, &str)]) -> Preconditions {
+        let mut header_map = HeaderMap::default();
+
+        for (k, v) in headers {
+            header_map.insert_by_str_key_value(k, v).unwrap();
+        }
+
+        Preconditions::from_headers(&header_map)
+    }
+
+    #[test]
+    fn parse_entity_tags() {
+        let strong: EntityTag = "\"abc\"".parse().unwrap();
+        let weak: EntityTag = "W/\"abc\"".parse().unwrap();
+
+        assert!(!strong.is_weak());
+        assert!(weak.is_weak());
+        assert!(strong.weak_eq(&weak));
+        assert!(!strong.strong_eq(&weak));
+        assert_eq!(weak.to_string(), "W/\"abc\"");
+        assert_eq!(strong.to_weak(), weak);
+
+        assert_eq!(EntityTag::new("abc", true).unwrap(), weak);
+        assert_eq!(
+            EntityTag::new("abc", false).unwrap(),
+            EntityTag::strong("abc")
+        );
+
+        for invalid in ["a b", "a\"b", "a\u{7f}", "caf\u{e9}"] {
+            assert!(EntityTag::new(invalid, false).is_err());
+        }
+        assert!("abc".parse::<EntityTag>().is_err());
+        assert_eq!(
+            EntityTag::from_bytes(b"hello"),
+            EntityTag::from_bytes(b"hello")
+        );
+        assert_ne!(
+            EntityTag::from_bytes(b"hello"),
+            EntityTag::from_bytes(b"world")
+        );
+    }
+
+    #[test]
+    fn evaluate_preconditions() {
+        let etag = EntityTag::strong("v2");
+        let modified = HttpDate::from_unix_secs(1_000_000);
+
+        let eval = |headers: &[(&str, &str)], method: Method| {
+            preconditions(headers).evaluate(&method, Some(&etag), Some(modified))
+        };
+
+        assert_eq!(
+            eval(&[("If-None-Match", "\"v1\", W/\"v2\"")], Method::GET),
+            Some(HttpStatus::NotModified)
+        );
+        assert_eq!(eval(&[("If-None-Match", "\"v1\"")], Method::GET), None);
+        assert_eq!(
+            eval(&[("If-None-Match", "*")], Method::PUT),
+            Some(HttpStatus::PreconditionFailed)
+        );
+        assert_eq!(eval(&[("If-Match", "\"v2\"")], Method::PUT), None);
+        assert_eq!(eval(&[("If-Match", "*")], Method::PUT), None);
+        assert_eq!(
+            eval(&[("If-Match", "W/\"v2\"")], Method::PUT),
+            Some(HttpStatus::PreconditionFailed)
+        );
+
+        let before = HttpDate::from_unix_secs(999_999).to_string();
+        let after = HttpDate::from_unix_secs(1_000_001).to_string();
+
+        assert_eq!(
+            eval(&[("If-Modified-Since", &after)], Method::GET),
+            Some(HttpStatus::NotModified)
+        );
+        assert_eq!(eval(&[("If-Modified-Since", &before)], Method::GET), None);
+        assert_eq!(eval(&[("If-Modified-Since", &after)], Method::POST), None);
+        assert_eq!(
+            eval(&[("If-Unmodified-Since", &before)], Method::DELETE),
+            Some(HttpStatus::PreconditionFailed)
+        );
+
+        // `*` only matches a resource that exists.
+        let missing =
+            |headers: &[(&str, &str)]| preconditions(headers).evaluate(&Method::PUT, None, None);
+
+        assert_eq!(
+            missing(&[("If-Match", "*")]),
+            Some(HttpStatus::PreconditionFailed)
+        );
+        assert_eq!(missing(&[("If-None-Match", "*")]), None);
+
+        // If-None-Match takes precedence over If-Modified-Since.
+        assert_eq!(
+            eval(
+                &[("If-None-Match", "\"v1\""), ("If-Modified-Since", &after)],
+                Method::GET
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn apply_not_modified() {
+        let res = || {
+            ResponseBuilder::text("Hello")
+                .etag(&EntityTag::strong("v1"))
+                .header(
+                    HeaderName::CACHE_CONTROL,
+                    HeaderValue::from_static("max-age=60"),
+                )
+                .build()
+        };
+
+        let res = preconditions(&[("If-None-Match", "\"v1\"")]).apply(&Method::GET, res());
+
+        assert_eq!(res.header.status_code, 304);
+        assert!(res.body.contents.is_empty());
+
+        let header_map = res.header.header_map();
+        assert_eq!(header_map.get_by_str_key("etag"), Some("\"v1\""));
+        assert_eq!(
+            header_map.get_by_str_key("cache-control"),
+            Some("max-age=60")
+        );
+        assert_eq!(header_map.get_by_str_key("content-length"), None);
+        assert_eq!(header_map.get_by_str_key("content-type"), None);
+    }
+}
@@ -0,0 +1,902 @@
+use std::{slice::Iter, str::FromStr, time::Duration};
+
+use crate::{
+    crypto,
+    error::{HttpError, HttpInternalError},
+    extract::FromRequest,
+    header_item::HeaderItem,
+    header_map::HeaderMap,
+    header_name::HeaderName,
+    header_value::HeaderValue,
+    http_date::HttpDate,
+    request::{Request, ServerRequest},
+    response::{Response, ResponseBuilder},
+    Result,
+};
+
+/// The `SameSite` attribute of a cookie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl std::fmt::Display for SameSite {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            SameSite::Strict => "Strict",
+            SameSite::Lax => "Lax",
+            SameSite::None => "None",
+        };
+
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for SameSite {
+    type Err = HttpInternalError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "strict" => Ok(SameSite::Strict),
+            "lax" => Ok(SameSite::Lax),
+            "none" => Ok(SameSite::None),
+            _ => Err(HttpInternalError::new("Unknown SameSite value.")),
+        }
+    }
+}
+
+/// An HTTP cookie. Cookies sent by a client only have a name and value, while cookies set by a
+/// server with `Set-Cookie` may also have attributes.
+///
+/// Formats as a `Set-Cookie` value and parses one with `FromStr`, ignoring unknown attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<Duration>,
+    expires: Option<HttpDate>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    /// A cookie without attributes, failing if `name` or `value` is invalid.
+    pub fn new(name: &str, value: &str) -> Result<Self> {
+        CookieBuilder::new(name, value).build()
+    }
+
+    pub fn build(name: &str, value: &str) -> CookieBuilder {
+        CookieBuilder::new(name, value)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    pub fn expires(&self) -> Option<HttpDate> {
+        self.expires
+    }
+
+    pub fn secure(&self) -> bool {
+        self.secure
+    }
+
+    pub fn http_only(&self) -> bool {
+        self.http_only
+    }
+
+    pub fn same_site(&self) -> Option<SameSite> {
+        self.same_site
+    }
+}
+
+impl std::fmt::Display for Cookie {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+
+        if let Some(expires) = self.expires {
+            write!(f, "; Expires={}", expires)?;
+        }
+
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={}", same_site)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Cookie {
+    type Err = HttpInternalError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut attributes = s.split(';');
+
+        let (name, value) = attributes
+            .next()
+            .and_then(|p| p.split_once('='))
+            .ok_or_else(|| HttpInternalError::new("Invalid cookie."))?;
+
+        let mut builder = CookieBuilder::new(name.trim(), value.trim());
+
+        for attribute in attributes {
+            let (key, val) = match attribute.split_once('=') {
+                Some((key, val)) => (key.trim(), val.trim()),
+                None => (attribute.trim(), ""),
+            };
+
+            match key.to_ascii_lowercase().as_str() {
+                "path" if val.starts_with('/') => builder = builder.path(val),
+                "domain" if !val.is_empty() => builder = builder.domain(val),
+                "max-age" => {
+                    // A zero or negative age expires the cookie immediately.
+                    let secs = val.parse::<i64>().map(|s| s.max(0) as u64);
+
+                    if let Ok(secs) = secs {
+                        builder = builder.max_age(Duration::from_secs(secs));
+                    }
+                }
+                "expires" => {
+                    if let Ok(expires) = val.parse() {
+                        builder = builder.expires(expires);
+                    }
+                }
+                "secure" => builder = builder.secure(true),
+                "httponly" => builder = builder.http_only(true),
+                "samesite" => {
+                    if let Ok(same_site) = val.parse() {
+                        builder = builder.same_site(same_site);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        builder.build()
+    }
+}
+
+/// The cookie has been validated, so it cannot contain control characters.
+impl From<&Cookie> for HeaderValue {
+    fn from(cookie: &Cookie) -> Self {
+        HeaderValue::from_trusted(cookie.to_string())
+    }
+}
+
+/// Builds a `Cookie`, validating it in `build`.
+#[derive(Debug, Clone)]
+pub struct CookieBuilder {
+    cookie: Cookie,
+}
+
+impl CookieBuilder {
+    pub fn new(name: &str, value: &str) -> Self {
+        Self {
+            cookie: Cookie {
+                name: name.to_owned(),
+                value: value.to_owned(),
+                path: None,
+                domain: None,
+                max_age: None,
+                expires: None,
+                secure: false,
+                http_only: false,
+                same_site: None,
+            },
+        }
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.cookie.path = Some(path.to_owned());
+        self
+    }
+
+    /// A leading `.` is ignored.
+    pub fn domain(mut self, domain: &str) -> Self {
+        self.cookie.domain = Some(domain.trim_start_matches('.').to_ascii_lowercase());
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.cookie.max_age = Some(max_age);
+        self
+    }
+
+    pub fn expires(mut self, expires: HttpDate) -> Self {
+        self.cookie.expires = Some(expires);
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.cookie.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.cookie.http_only = http_only;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.cookie.same_site = Some(same_site);
+        self
+    }
+
+    /// Clears the value and expires the cookie, so that a client deletes it. The path and domain
+    /// must match the ones the cookie was set with.
+    pub fn expire(mut self) -> Self {
+        self.cookie.value.clear();
+        self.cookie.max_age = Some(Duration::ZERO);
+        self.cookie.expires = Some(HttpDate::from_unix_secs(0));
+        self
+    }
+
+    /// Fails if the name is not a token, the value, path or domain contain characters that are
+    /// not allowed, or `SameSite=None` is used without `Secure`.
+    pub fn build(self) -> Result<Cookie> {
+        let cookie = self.cookie;
+
+        let invalid = |what: &str, s: &str| {
+            Err(HttpInternalError::new(format!(
+                "Invalid cookie {} '{}'.",
+                what,
+                s.escape_debug()
+            )))
+        };
+
+        if cookie.name.parse::<HeaderName>().is_err() {
+            return invalid("name", &cookie.name);
+        }
+
+        let value = cookie
+            .value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(&cookie.value);
+
+        if !value.bytes().all(is_cookie_octet) {
+            return invalid("value", &cookie.value);
+        }
+
+        if let Some(path) = cookie.path.as_deref() {
+            if path.bytes().any(|b| b.is_ascii_control() || b == b';') || !path.is_ascii() {
+                return invalid("path", path);
+            }
+        }
+
+        if let Some(domain) = cookie.domain.as_deref() {
+            if domain.is_empty()
+                || !domain
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')
+            {
+                return invalid("domain", domain);
+            }
+        }
+
+        if cookie.same_site == Some(SameSite::None) && !cookie.secure {
+            return Err(HttpInternalError::new(
+                "Cookies with SameSite=None must be Secure.",
+            ));
+        }
+
+        Ok(cookie)
+    }
+}
+
+/// Whether `b` may appear in a cookie value, as defined by RFC 6265.
+fn is_cookie_octet(b: u8) -> bool {
+    matches!(b, 0x21 | 0x23..=0x2b | 0x2d..=0x3a | 0x3c..=0x5b | 0x5d..=0x7e)
+}
+
+/// A secret key for signing and encrypting cookies.
+///
+/// Cookies can only be verified or decrypted with the key they were signed or encrypted with, so
+/// the secret should be kept private and stay the same across restarts.
+#[derive(Clone)]
+pub struct Key {
+    signing: [u8; 32],
+    encryption: [u8; 32],
+    authentication: [u8; 32],
+}
+
+impl Key {
+    /// Derives a key from `secret`, failing if it is shorter than 32 bytes.
+    pub fn from_secret(secret: &[u8]) -> Result<Self> {
+        if secret.len() < 32 {
+            return Err(HttpInternalError::new(
+                "Cookie secrets must be at least 32 bytes.",
+            ));
+        }
+
+        Ok(Self::derive(secret))
+    }
+
+    /// A random key. Cookies from before a restart cannot be read with a new one.
+    pub fn generate() -> Self {
+        Self::derive(&crypto::random_bytes::<32>())
+    }
+
+    fn derive(secret: &[u8]) -> Self {
+        Self {
+            signing: crypto::hmac_sha256(secret, b"cookie signing"),
+            encryption: crypto::hmac_sha256(secret, b"cookie encryption"),
+            authentication: crypto::hmac_sha256(secret, b"cookie authentication"),
+        }
+    }
+
+    /// Appends an HMAC-SHA256 signature to the value of `cookie`. The value stays readable by the
+    /// client, but any change to it is detected by `verify`.
+    pub fn sign(&self, mut cookie: Cookie) -> Cookie {
+        let signature = self.signature(&cookie.name, &cookie.value);
+
+        cookie.value = format!("{}.{}", cookie.value, crypto::hex_encode(&signature));
+        cookie
+    }
+
+    /// The cookie with its original value if it was signed with this key under the same name.
+    pub fn verify(&self, cookie: &Cookie) -> Option<Cookie> {
+        let (value, signature) = cookie.value.rsplit_once('.')?;
+
+        let signature = crypto::hex_decode(signature)?;
+
+        crypto::constant_time_eq(&signature, &self.signature(&cookie.name, value)).then(|| {
+            let mut cookie = cookie.clone();
+            cookie.value = value.to_owned();
+            cookie
+        })
+    }
+
+    /// Encrypts the value of `cookie` with ChaCha20 and authenticates it with HMAC-SHA256, so the
+    /// client can neither read nor change it.
+    pub fn encrypt(&self, mut cookie: Cookie) -> Cookie {
+        let nonce = crypto::random_bytes::<12>();
+
+        let mut ciphertext = std::mem::take(&mut cookie.value).into_bytes();
+        crypto::chacha20(&self.encryption, &nonce, 1, &mut ciphertext);
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+
+        let tag = self.tag(&cookie.name, &sealed);
+        sealed.extend_from_slice(&tag);
+
+        cookie.value = crypto::hex_encode(&sealed);
+        cookie
+    }
+
+    /// The cookie with its original value if it was encrypted with this key under the same name.
+    pub fn decrypt(&self, cookie: &Cookie) -> Option<Cookie> {
+        let sealed = crypto::hex_decode(&cookie.value)?;
+
+        if sealed.len() < 12 + 32 {
+            return None;
+        }
+
+        let (sealed, tag) = sealed.split_at(sealed.len() - 32);
+
+        if !crypto::constant_time_eq(tag, &self.tag(&cookie.name, sealed)) {
+            return None;
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(12);
+
+        let mut plaintext = ciphertext.to_vec();
+        crypto::chacha20(&self.encryption, nonce.try_into().ok()?, 1, &mut plaintext);
+
+        let mut cookie = cookie.clone();
+        cookie.value = String::from_utf8(plaintext).ok()?;
+
+        Some(cookie)
+    }
+
+    /// Cookie names are tokens, so they cannot contain the `=` separating them from the value.
+    fn signature(&self, name: &str, value: &str) -> [u8; 32] {
+        crypto::hmac_sha256(&self.signing, format!("{}={}", name, value).as_bytes())
+    }
+
+    fn tag(&self, name: &str, sealed: &[u8]) -> [u8; 32] {
+        let mut message = format!("{}=", name).into_bytes();
+        message.extend_from_slice(sealed);
+
+        crypto::hmac_sha256(&self.authentication, &message)
+    }
+}
+
+impl std::fmt::Debug for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Key").finish_non_exhaustive()
+    }
+}
+
+/// The cookies sent with a request in its `Cookie` headers. Cookies that are not valid are
+/// skipped.
+///
+/// As an extractor it never fails, and is empty if the request has no cookies.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CookieJar(Vec<Cookie>);
+
+impl CookieJar {
+    pub fn from_header_map(header_map: &HeaderMap) -> Self {
+        let cookies = header_map
+            .get_all("cookie")
+            .flat_map(|v| v.split(';'))
+            .filter_map(|pair| {
+                let (name, value) = pair.split_once('=')?;
+
+                Cookie::new(name.trim(), value.trim()).ok()
+            })
+            .collect();
+
+        Self(cookies)
+    }
+
+    /// The first cookie named `name`.
+    pub fn get(&self, name: &str) -> Option<&Cookie> {
+        self.0.iter().find(|c| c.name == name)
+    }
+
+    /// The cookie named `name`, if it was signed with `key`.
+    pub fn get_signed(&self, name: &str, key: &Key) -> Option<Cookie> {
+        self.get(name).and_then(|c| key.verify(c))
+    }
+
+    /// The cookie named `name` with its value decrypted, if it was encrypted with `key`.
+    pub fn get_private(&self, name: &str, key: &Key) -> Option<Cookie> {
+        self.get(name).and_then(|c| key.decrypt(c))
+    }
+
+    pub fn iter(&self) -> Iter<'_, Cookie> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<'a> IntoIterator for &'a CookieJar {
+    type Item = &'a Cookie;
+    type IntoIter = Iter<'a, Cookie>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl FromRequest for CookieJar {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        Ok(req.cookies())
+    }
+}
+
+impl ServerRequest {
+    /// The cookies sent with this request.
+    pub fn cookies(&self) -> CookieJar {
+        CookieJar::from_header_map(self.request.header.header_map())
+    }
+}
+
+impl ResponseBuilder {
+    /// Adds a `Set-Cookie` header, keeping any cookies already set.
+    pub fn cookie(self, cookie: &Cookie) -> Self {
+        self.append_header(HeaderName::SET_COOKIE, cookie.into())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct StoredCookie {
+    cookie: Cookie,
+    domain: String,
+    host_only: bool,
+    path: String,
+    expires: Option<HttpDate>,
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: HttpDate) -> bool {
+        self.expires.is_some_and(|e| e <= now)
+    }
+
+    fn matches(&self, host: &str, path: &str) -> bool {
+        let domain_matches = if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(host, &self.domain)
+        };
+
+        // Requests are never sent over TLS, so `Secure` cookies are kept but not sent.
+        domain_matches && path_matches(path, &self.path) && !self.cookie.secure
+    }
+}
+
+/// Stores cookies set by servers and replays them on later requests, following the domain and
+/// path rules of RFC 6265. Used by `Client::cookie_store`.
+///
+/// There is no public suffix list, so a `Domain` attribute without a `.`, such as `com`, is
+/// rejected unless it is the request host.
+#[derive(Debug, Clone, Default)]
+pub struct CookieStore(Vec<StoredCookie>);
+
+impl CookieStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `cookie` as if it had been set by a response to a request for `host` and `path`,
+    /// returning whether it was accepted. An expired cookie removes any stored cookie it
+    /// replaces.
+    pub fn store(&mut self, cookie: Cookie, host: &str, path: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+
+        let (domain, host_only) = match cookie.domain() {
+            Some(domain) if domain == host => (host.clone(), false),
+            Some(domain) if domain.contains('.') && domain_matches(&host, domain) => {
+                (domain.to_owned(), false)
+            }
+            Some(_) => return false,
+            None => (host.clone(), true),
+        };
+
+        let path = match cookie.path() {
+            Some(path) => path.to_owned(),
+            None => default_path(path).to_owned(),
+        };
+
+        let now = HttpDate::now();
+
+        let expires = match cookie.max_age() {
+            Some(max_age) => Some(HttpDate::from_unix_secs(
+                now.unix_secs().saturating_add(max_age.as_secs()),
+            )),
+            None => cookie.expires(),
+        };
+
+        let stored = StoredCookie {
+            cookie,
+            domain,
+            host_only,
+            path,
+            expires,
+        };
+
+        self.0.retain(|c| {
+            !(c.cookie.name == stored.cookie.name
+                && c.domain == stored.domain
+                && c.path == stored.path)
+        });
+
+        if stored.is_expired(now) {
+            return true;
+        }
+
+        self.0.push(stored);
+
+        true
+    }
+
+    /// Stores every cookie in the `Set-Cookie` headers of `response`.
+    pub fn store_response(&mut self, response: &Response, host: &str, path: &str) {
+        let cookies = response
+            .header
+            .header_map()
+            .get_all("set-cookie")
+            .filter_map(|v| v.parse::<Cookie>().ok())
+            .collect::<Vec<_>>();
+
+        for cookie in cookies {
+            self.store(cookie, host, path);
+        }
+    }
+
+    /// The unexpired cookies to send with a request for `host` and `path`, with longer paths
+    /// first.
+    pub fn matching(&self, host: &str, path: &str) -> Vec<&Cookie> {
+        let host = host.to_ascii_lowercase();
+        let path = path.split('?').next().unwrap_or_default();
+        let now = HttpDate::now();
+
+        let mut cookies = self
+            .0
+            .iter()
+            .filter(|c| !c.is_expired(now) && c.matches(&host, path))
+            .collect::<Vec<_>>();
+
+        cookies.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+
+        cookies.into_iter().map(|c| &c.cookie).collect()
+    }
+
+    /// Sets the `Cookie` header of `request` to the stored cookies for `host`, unless the request
+    /// already has one.
+    pub fn add_to_request(&self, request: &mut Request, host: &str) {
+        let cookies = self.matching(host, request.header.path());
+
+        let header_map = request.header.header_map_mut();
+
+        if cookies.is_empty() || header_map.contains_key("cookie") {
+            return;
+        }
+
+        let value = cookies
+            .iter()
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        header_map.insert(HeaderName::COOKIE, HeaderValue::from_trusted(value));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cookie> {
+        self.0.iter().map(|c| &c.cookie)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+fn domain_matches(host: &str, domain: &str) -> bool {
+    let is_ip = host.parse::<std::net::IpAddr>().is_ok() || host.starts_with('[');
+
+    host == domain
+        || (!is_ip
+            && host.len() > domain.len()
+            && host.ends_with(domain)
+            && host.as_bytes()[host.len() - domain.len() - 1] == b'.')
+}
+
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path.as_bytes()[cookie_path.len()] == b'/'))
+}
+
+/// The directory of `request_path`, used when a cookie has no `Path` attribute.
+fn default_path(request_path: &str) -> &str {
+    let path = request_path.split('?').next().unwrap_or_default();
+
+    match path.rfind('/') {
+        Some(i) if i > 0 && path.starts_with('/') => &path[..i],
+        _ => "/",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use crate::{
+        header_item::HeaderItem,
+        header_map::HeaderMap,
+        http_date::HttpDate,
+        request::{RequestBuilder, ServerRequest},
+        response::ResponseBuilder,
+        route::RouteKey,
+    };
+
+    use super::{Cookie, CookieJar, CookieStore, Key, SameSite};
+
+    #[test]
+    fn set_cookies() {
+        let session = Cookie::build("session", "abc123")
+            .path("/")
+            .domain(".Example.com")
+            .max_age(Duration::from_secs(3600))
+            .expires(HttpDate::from_unix_secs(784111777))
+            .secure(true)
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .build()
+            .unwrap();
+
+        let res = ResponseBuilder::new()
+            .cookie(&session)
+            .cookie(
+                &Cookie::build("theme", "")
+                    .path("/")
+                    .expire()
+                    .build()
+                    .unwrap(),
+            )
+            .build();
+
+        assert_eq!(
+            res.header
+                .header_map()
+                .get_all("set-cookie")
+                .collect::<Vec<_>>(),
+            [
+                "session=abc123; Path=/; Domain=example.com; Max-Age=3600; \
+                 Expires=Sun, 06 Nov 1994 08:49:37 GMT; Secure; HttpOnly; SameSite=Lax",
+                "theme=; Path=/; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+            ]
+        );
+
+        assert_eq!(session.to_string().parse::<Cookie>().unwrap(), session);
+
+        assert!(Cookie::new("a b", "1").is_err());
+        assert!(Cookie::new("id", "1; Path=/").is_err());
+        assert!(Cookie::build("id", "1").path("/\r\n").build().is_err());
+        assert!(Cookie::build("id", "1")
+            .same_site(SameSite::None)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn request_cookies() {
+        let req = ServerRequest::new(
+            RouteKey("/".to_owned()),
+            RequestBuilder::new()
+                .insert_header_key_val("Cookie", "session=abc; theme=\"dark\"; bad cookie=1")
+                .unwrap()
+                .build(),
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+        );
+
+        let cookies = req.cookies();
+
+        assert_eq!(cookies.len(), 2);
+        assert_eq!(cookies.get("session").unwrap().value(), "abc");
+        assert_eq!(cookies.get("theme").unwrap().value(), "\"dark\"");
+        assert!(cookies.get("missing").is_none());
+    }
+
+    #[test]
+    fn cookie_store() {
+        let mut store = CookieStore::new();
+
+        let res = ResponseBuilder::new()
+            .insert_header_key_val("Set-Cookie", "id=1; Domain=example.com; Path=/")
+            .unwrap()
+            .build();
+
+        store.store_response(&res, "www.example.com", "/login");
+
+        let cookie = |s: &str| s.parse::<Cookie>().unwrap();
+
+        assert!(store.store(cookie("page=2"), "example.com", "/docs/intro"));
+        assert!(store.store(cookie("token=x; Secure"), "example.com", "/"));
+        assert!(!store.store(cookie("a=1; Domain=other.com"), "example.com", "/"));
+        assert!(!store.store(cookie("a=1; Domain=com"), "example.com", "/"));
+
+        let names = |store: &CookieStore, host: &str, path: &str| {
+            store
+                .matching(host, path)
+                .iter()
+                .map(|c| c.name().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names(&store, "api.example.com", "/"), ["id"]);
+        assert_eq!(
+            names(&store, "example.com", "/docs/intro?x=1"),
+            ["page", "id"]
+        );
+        assert_eq!(names(&store, "example.com", "/docsearch"), ["id"]);
+        assert!(names(&store, "example.org", "/").is_empty());
+
+        let mut req = RequestBuilder::new().uri("/docs/").build();
+        store.add_to_request(&mut req, "Example.com");
+        assert_eq!(
+            req.header.header_map().get_by_str_key("cookie"),
+            Some("page=2; id=1")
+        );
+
+        store.store(
+            cookie("id=; Domain=example.com; Path=/; Max-Age=0"),
+            "example.com",
+            "/",
+        );
+        assert_eq!(names(&store, "example.com", "/"), Vec::<String>::new());
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn signed_and_private_cookies() {
+        let key = Key::from_secret(&[7; 32]).unwrap();
+        let other = Key::generate();
+
+        assert!(Key::from_secret(b"too short").is_err());
+
+        let signed = key.sign(Cookie::build("user", "zak").path("/").build().unwrap());
+        let private = key.encrypt(Cookie::new("cart", "3-items").unwrap());
+
+        assert!(signed.value().starts_with("zak."));
+        assert!(!private.value().contains("3-items"));
+        assert_ne!(
+            key.encrypt(Cookie::new("cart", "3-items").unwrap()),
+            private
+        );
+
+        let jar = |cookies: &[&Cookie]| {
+            let header = cookies
+                .iter()
+                .map(|c| format!("{}={}", c.name(), c.value()))
+                .collect::<Vec<_>>()
+                .join("; ");
+
+            let mut header_map = HeaderMap::new();
+            header_map
+                .insert_by_str_key_value("Cookie", &header)
+                .unwrap();
+
+            CookieJar::from_header_map(&header_map)
+        };
+
+        let cookies = jar(&[&signed, &private]);
+
+        assert_eq!(cookies.get_signed("user", &key).unwrap().value(), "zak");
+        assert_eq!(
+            cookies.get_private("cart", &key).unwrap().value(),
+            "3-items"
+        );
+        assert!(cookies.get_signed("user", &other).is_none());
+        assert!(cookies.get_private("cart", &other).is_none());
+        assert!(cookies.get_private("user", &key).is_none());
+
+        let tampered = Cookie::new("user", &signed.value().replacen("zak", "admin", 1)).unwrap();
+        let renamed = Cookie::new("admin", signed.value()).unwrap();
+        let mut flipped = private.value().to_owned().into_bytes();
+        flipped[30] = if flipped[30] == b'0' { b'1' } else { b'0' };
+        let flipped = Cookie::new("cart", std::str::from_utf8(&flipped).unwrap()).unwrap();
+
+        assert!(key.verify(&tampered).is_none());
+        assert!(key.verify(&renamed).is_none());
+        assert!(key.decrypt(&flipped).is_none());
+    }
+}
@@ -0,0 +1,500 @@
+use std::time::Duration;
+
+use crate::{
+    header_item::HeaderItem,
+    header_map::HeaderMap,
+    header_name::HeaderName,
+    header_value::HeaderValue,
+    http_status::HttpStatus,
+    method::Method,
+    middleware::{Middleware, Next},
+    request::ServerRequest,
+    response::{Response, ResponseBuilder},
+};
+
+#[derive(Debug, Clone)]
+enum AllowOrigin {
+    Any,
+    List(Vec<OriginPattern>),
+}
+
+/// An allowed origin such as `https://example.com`, or a pattern with one `*` standing for any
+/// subdomains, such as `https://*.example.com`.
+#[derive(Debug, Clone)]
+struct OriginPattern {
+    prefix: String,
+    suffix: Option<String>,
+}
+
+impl OriginPattern {
+    fn new(pattern: &str) -> Self {
+        match pattern.split_once('*') {
+            Some((prefix, suffix)) => Self {
+                prefix: prefix.to_owned(),
+                suffix: Some(suffix.to_owned()),
+            },
+            None => Self {
+                prefix: pattern.trim_end_matches('/').to_owned(),
+                suffix: None,
+            },
+        }
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        let Some(suffix) = &self.suffix else {
+            return origin.eq_ignore_ascii_case(&self.prefix);
+        };
+
+        let len = self.prefix.len() + suffix.len();
+
+        origin.len() > len
+            && origin.is_char_boundary(self.prefix.len())
+            && origin.is_char_boundary(origin.len() - suffix.len())
+            && origin[..self.prefix.len()].eq_ignore_ascii_case(&self.prefix)
+            && origin[origin.len() - suffix.len()..].eq_ignore_ascii_case(suffix)
+            && origin[self.prefix.len()..origin.len() - suffix.len()]
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.')
+    }
+}
+
+/// Middleware implementing Cross-Origin Resource Sharing.
+///
+/// Preflight `OPTIONS` requests from allowed origins are answered with `204 No Content` before
+/// they reach the router, and preflights that ask for a disallowed origin, method or header get
+/// `403 Forbidden`. Other requests from allowed origins get the CORS headers added to their
+/// response. Requests without an `Origin` header are passed through unchanged.
+///
+/// Unless any origin is allowed, every response gets `Vary: Origin`, as the CORS headers then
+/// depend on the origin and caches must not share them between origins.
+///
+/// No origins are allowed by default.
+#[derive(Debug, Clone)]
+pub struct Cors {
+    origins: AllowOrigin,
+    methods: Vec<Method>,
+    headers: Option<Vec<HeaderName>>,
+    expose_headers: Vec<HeaderName>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl std::default::Default for Cors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cors {
+    pub fn new() -> Self {
+        Self {
+            origins: AllowOrigin::List(Vec::new()),
+            methods: vec![Method::GET, Method::HEAD, Method::POST],
+            headers: Some(Vec::new()),
+            expose_headers: Vec::new(),
+            credentials: false,
+            max_age: None,
+        }
+    }
+
+    /// Allows an origin such as `https://example.com`, or every origin matching a pattern such as
+    /// `https://*.example.com`. Replaces an earlier `allow_any_origin`.
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        match &mut self.origins {
+            AllowOrigin::Any => self.origins = AllowOrigin::List(vec![OriginPattern::new(origin)]),
+            AllowOrigin::List(origins) => origins.push(OriginPattern::new(origin)),
+        }
+
+        self
+    }
+
+    /// Replaces the origins allowed so far.
+    ///
+    /// # Panics
+    ///
+    /// Panics if credentials are allowed, as any site could then make credentialed requests.
+    pub fn allow_any_origin(mut self) -> Self {
+        assert!(
+            !self.credentials,
+            "CORS cannot allow any origin together with credentials."
+        );
+
+        self.origins = AllowOrigin::Any;
+        self
+    }
+
+    /// The methods allowed in preflights. Defaults to `GET`, `HEAD` and `POST`.
+    pub fn allow_methods<I: IntoIterator<Item = Method>>(mut self, methods: I) -> Self {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// The request headers allowed in preflights.
+    pub fn allow_headers<I: IntoIterator<Item = HeaderName>>(mut self, headers: I) -> Self {
+        self.headers = Some(headers.into_iter().collect());
+        self
+    }
+
+    /// Allows whichever request headers a preflight asks for.
+    pub fn allow_any_header(mut self) -> Self {
+        self.headers = None;
+        self
+    }
+
+    /// Response headers that scripts are allowed to read.
+    pub fn expose_headers<I: IntoIterator<Item = HeaderName>>(mut self, headers: I) -> Self {
+        self.expose_headers = headers.into_iter().collect();
+        self
+    }
+
+    /// Allows cookies and `Authorization` headers to be sent from the allowed origins.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any origin is allowed, as any site could then make credentialed requests.
+    pub fn allow_credentials(mut self, credentials: bool) -> Self {
+        assert!(
+            !(credentials && matches!(self.origins, AllowOrigin::Any)),
+            "CORS cannot allow credentials together with any origin."
+        );
+
+        self.credentials = credentials;
+        self
+    }
+
+    /// How long browsers may cache a preflight response.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    fn is_allowed_origin(&self, origin: &str) -> bool {
+        match &self.origins {
+            AllowOrigin::Any => true,
+            AllowOrigin::List(origins) => origins.iter().any(|o| o.matches(origin)),
+        }
+    }
+
+    /// Whether the CORS headers depend on the request origin, rather than always being `*`.
+    fn varies_by_origin(&self) -> bool {
+        !matches!(self.origins, AllowOrigin::Any)
+    }
+
+    fn join<T: ToString>(items: &[T]) -> HeaderValue {
+        HeaderValue::from_trusted(
+            items
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+    }
+
+    fn add_origin(&self, header_map: &mut HeaderMap, origin: &HeaderValue) {
+        if self.varies_by_origin() {
+            header_map.insert(HeaderName::ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+        } else {
+            header_map.insert(
+                HeaderName::ACCESS_CONTROL_ALLOW_ORIGIN,
+                HeaderValue::from_static("*"),
+            );
+        }
+
+        if self.credentials {
+            header_map.insert(
+                HeaderName::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                HeaderValue::from_static("true"),
+            );
+        }
+    }
+
+    fn preflight(&self, header_map: &HeaderMap, origin: &HeaderValue) -> Response {
+        let forbidden = || ResponseBuilder::new().status(HttpStatus::Forbidden).build();
+
+        let method_allowed = header_map
+            .get_by_str_key_as::<Method>("access-control-request-method")
+            .is_some_and(|m| self.methods.contains(&m));
+
+        let requested_headers = header_map
+            .get_all("access-control-request-headers")
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .filter(|h| !h.is_empty())
+            .collect::<Vec<_>>();
+
+        let headers_allowed = match &self.headers {
+            None => requested_headers
+                .iter()
+                .all(|h| h.parse::<HeaderName>().is_ok()),
+            Some(allowed) => requested_headers
+                .iter()
+                .all(|h| allowed.iter().any(|a| a == h)),
+        };
+
+        if !self.is_allowed_origin(origin.as_str()) || !method_allowed || !headers_allowed {
+            return forbidden();
+        }
+
+        let mut res = ResponseBuilder::new().status(HttpStatus::NoContent).build();
+
+        let res_headers = res.header.header_map_mut();
+
+        self.add_origin(res_headers, origin);
+
+        res_headers.insert(
+            HeaderName::ACCESS_CONTROL_ALLOW_METHODS,
+            Self::join(&self.methods),
+        );
+
+        let allowed_headers = match &self.headers {
+            None => Self::join(&requested_headers),
+            Some(allowed) => Self::join(allowed),
+        };
+
+        if !allowed_headers.as_str().is_empty() {
+            res_headers.insert(HeaderName::ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers);
+        }
+
+        if let Some(max_age) = self.max_age {
+            res_headers.insert(HeaderName::ACCESS_CONTROL_MAX_AGE, max_age.as_secs().into());
+        }
+
+        res_headers.add_vary(HeaderName::ACCESS_CONTROL_REQUEST_METHOD);
+        res_headers.add_vary(HeaderName::ACCESS_CONTROL_REQUEST_HEADERS);
+
+        res
+    }
+
+    /// Handles the request, adding every CORS header but `Vary`.
+    fn respond(&self, req: ServerRequest, next: Next<'_>) -> Response {
+        let header_map = req.request.header.header_map();
+
+        let Some(origin) = header_map.get("origin").cloned() else {
+            return next.run(req);
+        };
+
+        if req.request.header.method == Method::OPTIONS
+            && header_map.contains_key("access-control-request-method")
+        {
+            return self.preflight(header_map, &origin);
+        }
+
+        let mut res = next.run(req);
+
+        if self.is_allowed_origin(origin.as_str()) {
+            let res_headers = res.header.header_map_mut();
+
+            self.add_origin(res_headers, &origin);
+
+            if !self.expose_headers.is_empty() {
+                res_headers.insert(
+                    HeaderName::ACCESS_CONTROL_EXPOSE_HEADERS,
+                    Self::join(&self.expose_headers),
+                );
+            }
+        }
+
+        res
+    }
+}
+
+impl Middleware for Cors {
+    fn handle(&self, req: ServerRequest, next: Next<'_>) -> Response {
+        let mut res = self.respond(req, next);
+
+        if self.varies_by_origin() {
+            res.header.header_map_mut().add_vary(HeaderName::ORIGIN);
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use crate::{
+        header_item::HeaderItem,
+        header_name::HeaderName,
+        method::Method,
+        middleware::{Middleware, Next},
+        request::{RequestBuilder, ServerRequest},
+        response::{Response, ResponseBuilder},
+        route::RouteKey,
+    };
+
+    use super::Cors;
+
+    fn send(cors: Cors, method: Method, headers: &[(&str, &str)]) -> Response {
+        let middleware: Vec<Box<dyn Middleware>> = vec![Box::new(cors)];
+
+        let endpoint = |_: ServerRequest| ResponseBuilder::text("Hello").build();
+
+        let mut req = RequestBuilder::new().method(method);
+
+        for (k, v) in headers {
+            req = req.insert_header_key_val(k, v).unwrap();
+        }
+
+        let req = ServerRequest::new(
+            RouteKey("/".to_owned()),
+            req.build(),
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+        );
+
+        Next::new(&middleware, &endpoint).run(req)
+    }
+
+    fn header<'a>(res: &'a Response, key: &str) -> Option<&'a str> {
+        res.header.header_map().get_by_str_key(key)
+    }
+
+    fn cors() -> Cors {
+        Cors::new()
+            .allow_origin("https://app.example.com")
+            .allow_origin("https://*.example.org")
+            .allow_methods([Method::GET, Method::PUT])
+            .allow_headers([HeaderName::CONTENT_TYPE, HeaderName::AUTHORIZATION])
+            .expose_headers([HeaderName::ETAG])
+            .allow_credentials(true)
+            .max_age(Duration::from_secs(600))
+    }
+
+    #[test]
+    fn preflight() {
+        let res = send(
+            cors(),
+            Method::OPTIONS,
+            &[
+                ("Origin", "https://app.example.com"),
+                ("Access-Control-Request-Method", "PUT"),
+                (
+                    "Access-Control-Request-Headers",
+                    "content-type, authorization",
+                ),
+            ],
+        );
+
+        assert_eq!(res.header.status_code, 204);
+        assert!(res.body.contents.is_empty());
+        assert_eq!(
+            header(&res, "access-control-allow-origin"),
+            Some("https://app.example.com")
+        );
+        assert_eq!(
+            header(&res, "access-control-allow-methods"),
+            Some("GET, PUT")
+        );
+        assert_eq!(
+            header(&res, "access-control-allow-headers"),
+            Some("Content-Type, Authorization")
+        );
+        assert_eq!(
+            header(&res, "access-control-allow-credentials"),
+            Some("true")
+        );
+        assert_eq!(header(&res, "access-control-max-age"), Some("600"));
+        assert!(res
+            .header
+            .header_map()
+            .contains_by_str_key_value("vary", "Origin"));
+
+        for headers in [
+            [
+                ("Origin", "https://evil.com"),
+                ("Access-Control-Request-Method", "PUT"),
+            ],
+            [
+                ("Origin", "https://app.example.com"),
+                ("Access-Control-Request-Method", "DELETE"),
+            ],
+            [
+                ("Origin", "https://app.example.com"),
+                ("Access-Control-Request-Headers", "X-Secret"),
+            ],
+        ] {
+            let mut headers = headers.to_vec();
+
+            if !headers
+                .iter()
+                .any(|(k, _)| *k == "Access-Control-Request-Method")
+            {
+                headers.push(("Access-Control-Request-Method", "GET"));
+            }
+
+            let res = send(cors(), Method::OPTIONS, &headers);
+
+            assert_eq!(res.header.status_code, 403, "{:?}", headers);
+            assert_eq!(header(&res, "access-control-allow-origin"), None);
+            assert_eq!(header(&res, "vary"), Some("Origin"));
+        }
+    }
+
+    #[test]
+    fn actual_requests() {
+        let res = send(
+            cors(),
+            Method::GET,
+            &[("Origin", "https://api.v2.example.org")],
+        );
+
+        assert_eq!(res.body.contents, b"Hello");
+        assert_eq!(
+            header(&res, "access-control-allow-origin"),
+            Some("https://api.v2.example.org")
+        );
+        assert_eq!(header(&res, "access-control-expose-headers"), Some("ETag"));
+
+        for origin in [
+            "https://example.org",
+            "https://evil.com/.example.org",
+            "http://api.example.org",
+        ] {
+            let res = send(cors(), Method::GET, &[("Origin", origin)]);
+
+            assert_eq!(res.body.contents, b"Hello");
+            assert_eq!(
+                header(&res, "access-control-allow-origin"),
+                None,
+                "{}",
+                origin
+            );
+            assert_eq!(header(&res, "vary"), Some("Origin"));
+        }
+
+        let res = send(
+            Cors::new().allow_any_origin(),
+            Method::GET,
+            &[("Origin", "null")],
+        );
+        assert_eq!(header(&res, "access-control-allow-origin"), Some("*"));
+        assert_eq!(header(&res, "vary"), None);
+        assert_eq!(header(&res, "access-control-allow-credentials"), None);
+
+        let res = send(
+            Cors::new()
+                .allow_any_origin()
+                .allow_origin("https://app.example.com"),
+            Method::GET,
+            &[("Origin", "https://evil.com")],
+        );
+        assert_eq!(header(&res, "access-control-allow-origin"), None);
+
+        let res = send(cors(), Method::GET, &[]);
+        assert_eq!(header(&res, "access-control-allow-origin"), None);
+        assert_eq!(header(&res, "vary"), Some("Origin"));
+    }
+
+    #[test]
+    #[should_panic(expected = "credentials")]
+    fn any_origin_with_credentials() {
+        let _ = Cors::new().allow_any_origin().allow_credentials(true);
+    }
+
+    #[test]
+    #[should_panic(expected = "credentials")]
+    fn credentials_with_any_origin() {
+        let _ = Cors::new().allow_credentials(true).allow_any_origin();
+    }
+}
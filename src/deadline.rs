@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+
+use crate::{error::HttpError, extract::FromRequest, request::ServerRequest};
+
+/// The time by which the server must respond to a request, set with `Server::timeout` or
+/// `Route::timeout`.
+///
+/// Once it passes the client has already been sent `503 Service Unavailable`, but the handler
+/// keeps running on its own thread. Handlers doing slow work should check `is_expired` between
+/// steps, or pass `remaining` on as the timeout of calls to other services, so they stop once
+/// nobody is waiting for the result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    expires: Instant,
+}
+
+impl Deadline {
+    pub fn after(timeout: Duration) -> Self {
+        Self {
+            expires: Instant::now() + timeout,
+        }
+    }
+
+    pub fn expires(&self) -> Instant {
+        self.expires
+    }
+
+    /// The time left, which is zero once the deadline has passed.
+    pub fn remaining(&self) -> Duration {
+        self.expires.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.expires
+    }
+
+    /// Fails with `504 Gateway Timeout` once the deadline has passed, so a handler waiting on
+    /// other services can give up with `?`.
+    pub fn check(&self) -> std::result::Result<(), HttpError> {
+        if self.is_expired() {
+            Err(HttpError::gateway_timeout(
+                "The request deadline has passed.",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl FromRequest for Deadline {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        req.deadline()
+            .ok_or_else(|| HttpError::internal_server_error("No timeout is set for this request."))
+    }
+}
+
+impl ServerRequest {
+    /// The deadline of this request, if the server or its route has a timeout.
+    pub fn deadline(&self) -> Option<Deadline> {
+        self.extensions().get::<Deadline>().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::http_status::HttpStatus;
+
+    use super::Deadline;
+
+    #[test]
+    fn expires() {
+        let deadline = Deadline::after(Duration::from_secs(60));
+
+        assert!(!deadline.is_expired());
+        assert!(deadline.remaining() > Duration::from_secs(59));
+        assert!(deadline.check().is_ok());
+
+        let deadline = Deadline::after(Duration::ZERO);
+
+        assert!(deadline.is_expired());
+        assert_eq!(deadline.remaining(), Duration::ZERO);
+        assert_eq!(
+            deadline.check().unwrap_err().status(),
+            HttpStatus::GatewayTimeout
+        );
+    }
+}
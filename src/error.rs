@@ -1,11 +1,23 @@
 use std::io::ErrorKind;
 
-use crate::{convert_error, http_status::HttpStatus};
+use crate::{
+    convert_error, header_map::HeaderMap, header_name::HeaderName, header_value::HeaderValue,
+    http_error_constructor, http_status::HttpStatus, mime::Mime,
+};
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct HttpError {
     message: String,
     status: HttpStatus,
+    headers: HeaderMap,
+    body: Option<ErrorBody>,
+}
+
+/// A response body to send instead of the plain text error message.
+#[derive(Debug, Eq, PartialEq)]
+pub struct ErrorBody {
+    pub content_type: Mime,
+    pub contents: Vec<u8>,
 }
 
 impl std::fmt::Display for HttpError {
@@ -21,8 +33,99 @@ impl HttpError {
         Self {
             message: message.as_ref().to_owned(),
             status,
+            headers: HeaderMap::new(),
+            body: None,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn status(&self) -> HttpStatus {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn body(&self) -> Option<&ErrorBody> {
+        self.body.as_ref()
+    }
+
+    /// Adds a header to the error response.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Replaces the plain text message in the error response with `body`.
+    pub fn with_body<T: AsRef<[u8]>>(mut self, content_type: Mime, body: T) -> Self {
+        self.body = Some(ErrorBody {
+            content_type,
+            contents: body.as_ref().to_vec(),
+        });
+        self
+    }
+
+    /// Responds with an RFC 7807 `application/problem+json` document built from the status and
+    /// message, using `about:blank` as the problem type.
+    pub fn problem(self) -> Self {
+        self.problem_with("about:blank", None)
+    }
+
+    pub fn problem_with(self, type_uri: &str, instance: Option<&str>) -> Self {
+        let mut doc = format!(
+            r#"{{"type":"{}","title":"{}","status":{},"detail":"{}""#,
+            escape_json(type_uri),
+            escape_json(&self.status.to_string()),
+            u16::from(self.status),
+            escape_json(&self.message)
+        );
+
+        if let Some(instance) = instance {
+            doc.push_str(&format!(r#","instance":"{}""#, escape_json(instance)));
+        }
+
+        doc.push('}');
+
+        self.with_body(Mime::APPLICATION_PROBLEM_JSON, doc)
+    }
+
+    http_error_constructor!(bad_request, HttpStatus::BadRequest);
+    http_error_constructor!(unauthorized, HttpStatus::Unauthorized);
+    http_error_constructor!(forbidden, HttpStatus::Forbidden);
+    http_error_constructor!(not_found, HttpStatus::NotFound);
+    http_error_constructor!(method_not_allowed, HttpStatus::MethodNotAllowed);
+    http_error_constructor!(not_acceptable, HttpStatus::NotAcceptable);
+    http_error_constructor!(conflict, HttpStatus::Conflict);
+    http_error_constructor!(gone, HttpStatus::Gone);
+    http_error_constructor!(payload_too_large, HttpStatus::PayloadTooLarge);
+    http_error_constructor!(unsupported_media_type, HttpStatus::UnsupportedMediaType);
+    http_error_constructor!(unprocessable_entity, HttpStatus::UnprocessableEntity);
+    http_error_constructor!(too_many_requests, HttpStatus::TooManyRequests);
+    http_error_constructor!(internal_server_error, HttpStatus::InternalServerError);
+    http_error_constructor!(service_unavailable, HttpStatus::ServiceUnavailable);
+    http_error_constructor!(gateway_timeout, HttpStatus::GatewayTimeout);
+}
+
+pub(crate) fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
         }
     }
+
+    escaped
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -66,6 +169,8 @@ convert_error!(std::str::Utf8Error);
 convert_error!(std::string::FromUtf8Error);
 convert_error!(std::num::TryFromIntError);
 convert_error!(std::env::VarError);
+#[cfg(feature = "json")]
+convert_error!(serde_json::Error);
 
 #[macro_export]
 macro_rules! convert_error {
@@ -77,3 +182,46 @@ macro_rules! convert_error {
         }
     };
 }
+
+#[macro_export]
+macro_rules! http_error_constructor {
+    ($name:ident, $status:path) => {
+        pub fn $name<T: AsRef<str>>(message: T) -> Self {
+            Self::new(message, $status)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        header_name::HeaderName, header_value::HeaderValue, http_status::HttpStatus, mime::Mime,
+    };
+
+    use super::HttpError;
+
+    #[test]
+    fn problem_details() {
+        let err = HttpError::not_found("No user \"zak\".")
+            .header(
+                HeaderName::CACHE_CONTROL,
+                HeaderValue::from_static("no-store"),
+            )
+            .problem_with("https://example.com/probs/missing", Some("/users/zak"));
+
+        assert_eq!(err.status(), HttpStatus::NotFound);
+        assert_eq!(err.message(), "No user \"zak\".");
+        assert_eq!(
+            err.headers().get_by_str_key("cache-control"),
+            Some("no-store")
+        );
+
+        let body = err.body().unwrap();
+
+        assert_eq!(body.content_type, Mime::APPLICATION_PROBLEM_JSON);
+        assert_eq!(
+            std::str::from_utf8(&body.contents).unwrap(),
+            r#"{"type":"https://example.com/probs/missing","title":"Not Found","status":404,"detail":"No user \"zak\".","instance":"/users/zak"}"#
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+type AnyMap = HashMap<TypeId, Box<dyn Any + Send + Sync>>;
+
+#[derive(Default)]
+pub struct Extensions {
+    map: AnyMap,
+}
+
+impl std::fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|prev| prev.downcast().ok())
+            .map(|prev| *prev)
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|v| v.downcast_ref())
+    }
+
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|v| v.downcast_mut())
+    }
+
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|v| v.downcast().ok())
+            .map(|v| *v)
+    }
+
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Extensions;
+
+    #[derive(Debug, PartialEq)]
+    struct AppState {
+        name: &'static str,
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let mut ext = Extensions::new();
+
+        assert!(ext.insert(AppState { name: "first" }).is_none());
+        assert!(ext.insert(5_u32).is_none());
+
+        let prev = ext.insert(AppState { name: "second" });
+
+        assert_eq!(prev, Some(AppState { name: "first" }));
+        assert_eq!(ext.get::<AppState>().map(|s| s.name), Some("second"));
+        assert_eq!(ext.get::<u32>(), Some(&5));
+        assert_eq!(ext.get::<u64>(), None);
+
+        if let Some(n) = ext.get_mut::<u32>() {
+            *n += 1;
+        }
+
+        assert_eq!(ext.remove::<u32>(), Some(6));
+        assert!(!ext.contains::<u32>());
+        assert_eq!(ext.len(), 1);
+    }
+}
@@ -0,0 +1,395 @@
+use std::{net::SocketAddr, ops::Deref, str::FromStr};
+
+use crate::{
+    error::HttpError,
+    header_item::HeaderItem,
+    http_status::HttpStatus,
+    params::{FromParams, Params},
+    request::ServerRequest,
+    typed_header::TypedHeader,
+};
+
+/// Types that can be built from an incoming request and used as handler arguments.
+///
+/// Extractors that consume the request body, such as `Bytes` and `Text`, take it out of the
+/// request, so any later extractor reading the body will see it empty.
+pub trait FromRequest: Sized {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError>;
+}
+
+impl<T: FromRequest> FromRequest for Option<T> {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        Ok(T::from_request(req).ok())
+    }
+}
+
+impl<T: FromRequest> FromRequest for std::result::Result<T, HttpError> {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        Ok(T::from_request(req))
+    }
+}
+
+/// Path parameters of the matched route.
+///
+/// A single value extracts the only named parameter of the route, a tuple extracts each of them in
+/// order and `Params` extracts them by name.
+#[derive(Debug)]
+pub struct Path<T>(pub T);
+
+pub trait FromPath: Sized {
+    fn from_path(params: &[(&str, &str)]) -> std::result::Result<Self, HttpError>;
+}
+
+fn parse_path_param<T: FromStr>(name: &str, value: &str) -> std::result::Result<T, HttpError> {
+    value.parse::<T>().map_err(|_| {
+        HttpError::new(
+            format!(
+                "Invalid path parameter type. Expected path parameter '{}' to be of type {}.",
+                name,
+                std::any::type_name::<T>()
+            ),
+            HttpStatus::BadRequest,
+        )
+    })
+}
+
+fn path_param_count_error(expected: usize, found: usize) -> HttpError {
+    HttpError::new(
+        format!(
+            "Expected {} path parameter(s) but the route has {}.",
+            expected, found
+        ),
+        HttpStatus::InternalServerError,
+    )
+}
+
+macro_rules! from_path_single {
+    ($($ty:ty),*) => {
+        $(
+            impl FromPath for $ty {
+                fn from_path(params: &[(&str, &str)]) -> std::result::Result<Self, HttpError> {
+                    match params {
+                        [(name, value)] => parse_path_param(name, value),
+                        _ => Err(path_param_count_error(1, params.len())),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_path_single!(
+    String, bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64
+);
+
+macro_rules! from_path_tuple {
+    ($len:literal; $($ty:ident),*) => {
+        impl<$($ty: FromStr),*> FromPath for ($($ty,)*) {
+            fn from_path(params: &[(&str, &str)]) -> std::result::Result<Self, HttpError> {
+                if params.len() != $len {
+                    return Err(path_param_count_error($len, params.len()));
+                }
+
+                let mut params = params.iter();
+
+                Ok(($({
+                    let (name, value) = params.next().copied().unwrap_or_default();
+                    parse_path_param::<$ty>(name, value)?
+                },)*))
+            }
+        }
+    };
+}
+
+from_path_tuple!(1; A);
+from_path_tuple!(2; A, B);
+from_path_tuple!(3; A, B, C);
+from_path_tuple!(4; A, B, C, D);
+
+impl FromPath for Params {
+    fn from_path(params: &[(&str, &str)]) -> std::result::Result<Self, HttpError> {
+        Ok(params.iter().copied().collect())
+    }
+}
+
+impl<T: FromPath> FromRequest for Path<T> {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        let params = req.path_params();
+
+        let params = params
+            .iter()
+            .map(|(k, v)| (*k, v.as_str()))
+            .collect::<Vec<_>>();
+
+        T::from_path(&params).map(Path)
+    }
+}
+
+impl<T> Deref for Path<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// The decoded query string. Missing query strings are treated as empty.
+#[derive(Debug)]
+pub struct Query<T>(pub T);
+
+impl<T: FromParams> FromRequest for Query<T> {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        T::from_params(&req.query()).map(Query)
+    }
+}
+
+impl<T> Deref for Query<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// An `application/x-www-form-urlencoded` request body.
+///
+/// Fails with `415 Unsupported Media Type` if the request has a different content type.
+#[derive(Debug)]
+pub struct Form<T>(pub T);
+
+impl<T: FromParams> FromRequest for Form<T> {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        req.request.form().map(Form)
+    }
+}
+
+impl<T> Deref for Form<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// A required request header, failing with a `400 Bad Request` if it is missing or invalid.
+/// Use `Option<Header<T>>` for optional headers.
+#[derive(Debug)]
+pub struct Header<T>(pub T);
+
+impl<T: TypedHeader> FromRequest for Header<T> {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        let header_map = req.request.header.header_map();
+
+        if !header_map.contains_key(T::NAME.as_str()) {
+            return Err(HttpError::new(
+                format!("Missing header '{}'.", T::NAME),
+                HttpStatus::BadRequest,
+            ));
+        }
+
+        header_map.typed_get().map(Header).ok_or_else(|| {
+            HttpError::new(
+                format!("Invalid value for header '{}'.", T::NAME),
+                HttpStatus::BadRequest,
+            )
+        })
+    }
+}
+
+impl<T> Deref for Header<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// The raw request body.
+#[derive(Debug)]
+pub struct Bytes(pub Vec<u8>);
+
+impl FromRequest for Bytes {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        Ok(Bytes(std::mem::take(&mut req.request.body.contents)))
+    }
+}
+
+/// The request body as UTF-8 text, failing with a `400 Bad Request` if it is not valid UTF-8.
+#[derive(Debug)]
+pub struct Text(pub String);
+
+impl FromRequest for Text {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        let contents = std::mem::take(&mut req.request.body.contents);
+
+        String::from_utf8(contents)
+            .map(Text)
+            .map_err(|_| HttpError::new("Request body is not valid UTF-8.", HttpStatus::BadRequest))
+    }
+}
+
+/// A clone of the application state registered with `Server::with_state`.
+#[derive(Debug)]
+pub struct State<T>(pub T);
+
+impl<T: Clone + Send + Sync + 'static> FromRequest for State<T> {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        req.state::<T>().cloned().map(State).ok_or_else(|| {
+            HttpError::new(
+                format!(
+                    "No state of type {} has been registered with the server.",
+                    std::any::type_name::<T>()
+                ),
+                HttpStatus::InternalServerError,
+            )
+        })
+    }
+}
+
+impl<T> Deref for State<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PeerAddr(pub SocketAddr);
+
+impl FromRequest for PeerAddr {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        Ok(PeerAddr(req.peer_address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use crate::{
+        error::HttpError,
+        header_name::HeaderName,
+        header_value::HeaderValue,
+        http_status::HttpStatus,
+        params::{FromParams, Params},
+        request::{RequestBuilder, ServerRequest},
+        route::RouteKey,
+        typed_header::TypedHeader,
+    };
+
+    use super::{Form, FromRequest, Header, Path, Query, Text};
+
+    fn server_request(route: &str, uri: &str) -> ServerRequest {
+        ServerRequest::new(
+            RouteKey(route.to_owned()),
+            RequestBuilder::new()
+                .uri(uri)
+                .typed_header(RequestId(42))
+                .body("Hello")
+                .build(),
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+        )
+    }
+
+    #[derive(Debug)]
+    struct RequestId(u32);
+
+    impl TypedHeader for RequestId {
+        const NAME: HeaderName = HeaderName::from_static("X-Request-Id");
+
+        fn decode(values: &[&str]) -> Option<Self> {
+            values[0].parse().ok().map(RequestId)
+        }
+
+        fn encode(&self) -> HeaderValue {
+            self.0.into()
+        }
+    }
+
+    #[test]
+    fn extract_path() {
+        let mut req = server_request("/hello/{name}/{age}", "/hello/Zak/24?x=1");
+
+        let Path((name, age)) = Path::<(String, u8)>::from_request(&mut req).unwrap();
+        assert_eq!((name.as_str(), age), ("Zak", 24));
+
+        let Path(params) = Path::<Params>::from_request(&mut req).unwrap();
+        assert_eq!(params.get("name"), Some("Zak"));
+
+        let err = Path::<(String, u8)>::from_request(&mut server_request(
+            "/hello/{name}/{age}",
+            "/hello/Zak/abc",
+        ))
+        .unwrap_err();
+        assert_eq!(err.status(), HttpStatus::BadRequest);
+
+        let err = Path::<u8>::from_request(&mut req).unwrap_err();
+        assert_eq!(err.status(), HttpStatus::InternalServerError);
+
+        let mut req = server_request("/hello/{name}/{age}", "/hello/Zak%20M%2Fx/24");
+
+        let Path((name, age)) = Path::<(String, u8)>::from_request(&mut req).unwrap();
+        assert_eq!((name.as_str(), age), ("Zak M/x", 24));
+        assert_eq!(req.path::<String>("name").unwrap(), "Zak M/x");
+
+        let mut req = server_request("/files/{*path}", "/files/a%20b/c.txt");
+
+        let Path(path) = Path::<String>::from_request(&mut req).unwrap();
+        assert_eq!(path, "a b/c.txt");
+    }
+
+    #[derive(Debug)]
+    struct Login {
+        user: String,
+        remember: bool,
+    }
+
+    impl FromParams for Login {
+        fn from_params(params: &Params) -> Result<Self, HttpError> {
+            Ok(Login {
+                user: params.required("user")?,
+                remember: params.get("remember") == Some("on"),
+            })
+        }
+    }
+
+    #[test]
+    fn extract_form() {
+        let form = Params::new().with("user", "zak m").with("remember", "on");
+
+        let mut req = ServerRequest::new(
+            RouteKey("/login".to_owned()),
+            RequestBuilder::new().uri("/login").form(&form).build(),
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+        );
+
+        assert_eq!(req.request.body.contents, b"user=zak+m&remember=on");
+
+        let Form(login) = Form::<Login>::from_request(&mut req).unwrap();
+        assert_eq!(login.user, "zak m");
+        assert!(login.remember);
+
+        let mut req = server_request("/login", "/login");
+        let err = Form::<Login>::from_request(&mut req).unwrap_err();
+        assert_eq!(err.status(), HttpStatus::UnsupportedMediaType);
+    }
+
+    #[test]
+    fn extract_query_header_body() {
+        let mut req = server_request("/search", "/search?q=rust+http&page=2");
+
+        let Query(params) = Query::<Params>::from_request(&mut req).unwrap();
+        assert_eq!(params.get("q"), Some("rust http"));
+        assert_eq!(params.get_as::<u8>("page"), Some(2));
+
+        let Header(RequestId(id)) = Header::<RequestId>::from_request(&mut req).unwrap();
+        assert_eq!(id, 42);
+
+        let Text(text) = Text::from_request(&mut req).unwrap();
+        assert_eq!(text, "Hello");
+
+        let Text(text) = Text::from_request(&mut req).unwrap();
+        assert_eq!(text, "");
+    }
+}
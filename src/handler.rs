@@ -0,0 +1,109 @@
+use crate::{
+    extract::FromRequest,
+    request::ServerRequest,
+    response::{HttpResponse, Response},
+};
+
+pub(crate) type HandlerFn = dyn Fn(ServerRequest) -> Response + Send + Sync;
+
+/// A request handler.
+///
+/// Implemented for functions and closures taking a `ServerRequest`, and for those taking up to
+/// eight `FromRequest` extractors. If an extractor fails, its `HttpError` is sent as the response
+/// and the handler is not called.
+pub trait Handler<Args>: Send + Sync + 'static {
+    fn call(&self, req: ServerRequest) -> Response;
+}
+
+impl<F, R> Handler<ServerRequest> for F
+where
+    F: Fn(ServerRequest) -> R + Send + Sync + 'static,
+    R: HttpResponse,
+{
+    fn call(&self, req: ServerRequest) -> Response {
+        Box::new((self)(req)).into_response()
+    }
+}
+
+macro_rules! extractor_handler {
+    ($($ty:ident),*) => {
+        impl<F, R, $($ty),*> Handler<($($ty,)*)> for F
+        where
+            F: Fn($($ty),*) -> R + Send + Sync + 'static,
+            R: HttpResponse,
+            $($ty: FromRequest,)*
+        {
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn call(&self, mut req: ServerRequest) -> Response {
+                $(
+                    let $ty = match $ty::from_request(&mut req) {
+                        Ok(v) => v,
+                        Err(e) => return Box::new(e).into_response(),
+                    };
+                )*
+
+                Box::new((self)($($ty),*)).into_response()
+            }
+        }
+    };
+}
+
+extractor_handler!();
+extractor_handler!(T1);
+extractor_handler!(T1, T2);
+extractor_handler!(T1, T2, T3);
+extractor_handler!(T1, T2, T3, T4);
+extractor_handler!(T1, T2, T3, T4, T5);
+extractor_handler!(T1, T2, T3, T4, T5, T6);
+extractor_handler!(T1, T2, T3, T4, T5, T6, T7);
+extractor_handler!(T1, T2, T3, T4, T5, T6, T7, T8);
+
+pub(crate) fn boxed<H, Args>(handler: H) -> Box<HandlerFn>
+where
+    H: Handler<Args>,
+    Args: 'static,
+{
+    Box::new(move |req| handler.call(req))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use crate::{
+        extract::{Path, PeerAddr, Text},
+        request::{RequestBuilder, ServerRequest},
+        route::RouteKey,
+    };
+
+    use super::{boxed, HandlerFn};
+
+    fn call(handler: &HandlerFn, uri: &str) -> (u16, Vec<u8>) {
+        let req = ServerRequest::new(
+            RouteKey("/hello/{age}".to_owned()),
+            RequestBuilder::new().uri(uri).body("Zak").build(),
+            SocketAddr::from(([127, 0, 0, 1], 8080)),
+        );
+
+        let res = handler(req);
+
+        (res.header.status_code, res.body.contents)
+    }
+
+    #[test]
+    fn call_extractor_handler() {
+        let handler = boxed(|Path(age): Path<u8>, Text(name): Text, PeerAddr(addr)| {
+            format!("{} {} {}", name, age, addr.port()).into_bytes()
+        });
+
+        assert_eq!(call(&*handler, "/hello/24"), (200, b"Zak 24 8080".to_vec()));
+        assert_eq!(call(&*handler, "/hello/abc").0, 400);
+    }
+
+    #[test]
+    fn call_request_handler() {
+        let handler = boxed(|req: ServerRequest| req.request.body.contents);
+
+        assert_eq!(call(&*handler, "/hello/24"), (200, b"Zak".to_vec()));
+    }
+}
@@ -1,91 +1,424 @@
 use std::{
-    collections::HashMap,
     io::Write,
-    ops::{Deref, DerefMut},
     str::{FromStr, Lines},
 };
 
-use crate::Result;
+use crate::{
+    header_name::HeaderName,
+    header_value::HeaderValue,
+    http_date::HttpDate,
+    typed_header::{TypedHeader, Vary},
+    Result,
+};
 
-#[derive(Debug, Eq)]
-pub struct HeaderKey(pub String);
+/// Headers in the order they were added. A name may have several values, such as repeated
+/// `Set-Cookie` headers, and names are compared case-insensitively.
+///
+/// Values of sensitive headers such as `Authorization` and `Cookie` are redacted from `Debug`
+/// output.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap(Vec<(HeaderName, HeaderValue)>);
 
-impl std::hash::Hash for HeaderKey {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.0.to_ascii_lowercase().hash(state);
+impl HeaderMap {
+    pub fn new() -> Self {
+        Self::default()
     }
-}
 
-impl std::cmp::PartialEq for HeaderKey {
-    fn eq(&self, other: &Self) -> bool {
-        self.0.eq_ignore_ascii_case(&other.0)
+    /// Parses `Name: value` lines, skipping any that are not valid headers.
+    pub fn from_lines(lines: Lines) -> Self {
+        let mut headers = HeaderMap::new();
+
+        for line in lines {
+            if let Some((k, v)) = line.split_once(':') {
+                if let (Ok(name), Ok(value)) = (k.trim().parse(), v.trim().parse()) {
+                    headers.append(name, value);
+                }
+            }
+        }
+
+        headers
     }
-}
 
-#[derive(Debug, Default)]
-pub struct HeaderMap(pub HashMap<HeaderKey, String>);
+    pub fn get<K: AsRef<str>>(&self, key: K) -> Option<&HeaderValue> {
+        self.0
+            .iter()
+            .find(|(k, _)| *k == key.as_ref())
+            .map(|(_, v)| v)
+    }
 
-impl HeaderMap {
-    pub fn from_lines(lines: Lines) -> Self {
-        let headers = lines.fold(HashMap::new(), |mut curr, next| {
-            let header = next.split_once(": ");
+    /// The first value of the header `key`.
+    pub fn get_by_str_key(&self, key: &str) -> Option<&str> {
+        self.get(key).map(|v| v.as_str())
+    }
+
+    pub fn get_by_str_key_as<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get_by_str_key(key).and_then(|v| v.parse().ok())
+    }
+
+    /// Every value of the header `key`, in order.
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.0
+            .iter()
+            .filter(move |(k, _)| *k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The header `H`, or `None` if it is missing or invalid.
+    pub fn typed_get<H: TypedHeader>(&self) -> Option<H> {
+        let name = H::NAME;
+        let values = self.get_all(name.as_str()).collect::<Vec<_>>();
 
-            if let Some((k, v)) = header {
-                curr.insert(HeaderKey(k.trim().to_owned()), v.to_owned());
+        if values.is_empty() {
+            None
+        } else {
+            H::decode(&values)
+        }
+    }
+
+    /// Sets the header `H` to `header`, replacing any existing values.
+    pub fn typed_insert<H: TypedHeader>(&mut self, header: H) {
+        self.insert(H::NAME, header.encode());
+    }
+
+    pub fn date(&self) -> Option<HttpDate> {
+        self.get_by_str_key_as("date")
+    }
+
+    pub fn last_modified(&self) -> Option<HttpDate> {
+        self.get_by_str_key_as("last-modified")
+    }
+
+    /// `None` if the header is missing or invalid, such as the commonly sent `Expires: 0`,
+    /// which caches treat as already expired.
+    pub fn expires(&self) -> Option<HttpDate> {
+        self.get_by_str_key_as("expires")
+    }
+
+    pub fn if_modified_since(&self) -> Option<HttpDate> {
+        self.get_by_str_key_as("if-modified-since")
+    }
+
+    pub fn if_unmodified_since(&self) -> Option<HttpDate> {
+        self.get_by_str_key_as("if-unmodified-since")
+    }
+
+    /// Sets the header `name` to `value`, replacing any existing values. The header keeps the
+    /// position of its first existing value.
+    pub fn insert(&mut self, name: HeaderName, value: HeaderValue) {
+        let value = Self::redact(&name, value);
+
+        match self.position(name.as_str()) {
+            Some(i) => {
+                self.0[i].1 = value;
+
+                let mut index = 0;
+
+                self.0.retain(|(k, _)| {
+                    let keep = index <= i || *k != name;
+                    index += 1;
+                    keep
+                });
             }
+            None => self.0.push((name, value)),
+        }
+    }
 
-            curr
-        });
+    /// Adds `value` to the header `name`, keeping any existing values.
+    pub fn append(&mut self, name: HeaderName, value: HeaderValue) {
+        let value = Self::redact(&name, value);
 
-        HeaderMap(headers)
+        self.0.push((name, value));
     }
 
-    pub fn get_by_str_key(&self, key: &str) -> Option<&str> {
-        let key = HeaderKey(key.to_owned());
+    /// Like `insert`, validating `key` and `value` first.
+    pub fn insert_by_str_key_value(&mut self, key: &str, value: &str) -> Result<()> {
+        self.insert(key.parse()?, value.parse()?);
 
-        self.0.get(&key).map(|k| k.as_str())
+        Ok(())
     }
 
-    pub fn get_by_str_key_as<T: FromStr>(&self, key: &str) -> Option<T> {
-        let key = HeaderKey(key.to_owned());
+    /// Adds `name` to the `Vary` header, unless it is already listed or the header is `*`.
+    pub fn add_vary(&mut self, name: HeaderName) {
+        if !self
+            .typed_get::<Vary>()
+            .is_some_and(|v| v.contains(name.as_str()))
+        {
+            self.append(
+                HeaderName::VARY,
+                HeaderValue::from_trusted(name.to_string()),
+            );
+        }
+    }
 
-        self.0.get(&key).and_then(|k| k.parse().ok())
+    /// Removes every value of the header `key`, returning the first.
+    pub fn remove(&mut self, key: &str) -> Option<HeaderValue> {
+        let first = self.position(key).map(|i| self.0.remove(i).1);
+
+        if first.is_some() {
+            self.0.retain(|(k, _)| *k != key);
+        }
+
+        first
     }
 
-    pub fn insert_by_str_key_value(&mut self, key: &str, value: &str) {
-        let key = HeaderKey(key.to_owned());
+    pub fn entry(&mut self, name: HeaderName) -> Entry<'_> {
+        match self.position(name.as_str()) {
+            Some(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            None => Entry::Vacant(VacantEntry { map: self, name }),
+        }
+    }
 
-        self.insert(key, value.to_owned());
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.position(key).is_some()
     }
 
     pub fn contains_by_str_key_value(&self, key: &str, value: &str) -> bool {
-        let key = HeaderKey(key.to_owned());
+        self.get_all(key).any(|v| v.eq_ignore_ascii_case(value))
+    }
 
-        self.0
-            .get(&key)
-            .map(|v| v.eq_ignore_ascii_case(value))
-            .unwrap_or(false)
+    /// Every header and value in order, with repeated headers yielded once per value.
+    pub fn iter(&self) -> impl Iterator<Item = (&HeaderName, &HeaderValue)> {
+        self.0.iter().map(|(k, v)| (k, v))
+    }
+
+    /// The number of values, counting each value of a repeated header.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
     }
 
     pub fn write_to<T: Write>(&self, writer: &mut T) -> Result<()> {
         for (k, v) in self.iter() {
-            write!(writer, "{}: {}\r\n", k.0, v)?;
+            write!(writer, "{}: {}\r\n", k, v)?;
         }
 
         Ok(())
     }
+
+    fn position(&self, key: &str) -> Option<usize> {
+        self.0.iter().position(|(k, _)| *k == key)
+    }
+
+    fn redact(name: &HeaderName, mut value: HeaderValue) -> HeaderValue {
+        if name.is_sensitive() {
+            value.set_sensitive(true);
+        }
+
+        value
+    }
 }
 
-impl Deref for HeaderMap {
-    type Target = HashMap<HeaderKey, String>;
+impl IntoIterator for HeaderMap {
+    type Item = (HeaderName, HeaderValue);
+    type IntoIter = std::vec::IntoIter<(HeaderName, HeaderValue)>;
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
     }
 }
 
-impl DerefMut for HeaderMap {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+impl FromIterator<(HeaderName, HeaderValue)> for HeaderMap {
+    fn from_iter<I: IntoIterator<Item = (HeaderName, HeaderValue)>>(iter: I) -> Self {
+        let mut headers = HeaderMap::new();
+
+        for (k, v) in iter {
+            headers.append(k, v);
+        }
+
+        headers
+    }
+}
+
+/// A header in a `HeaderMap`, returned by `HeaderMap::entry`.
+#[derive(Debug)]
+pub enum Entry<'a> {
+    Occupied(OccupiedEntry<'a>),
+    Vacant(VacantEntry<'a>),
+}
+
+impl<'a> Entry<'a> {
+    /// The first value of the header, inserting `value` if it is missing.
+    pub fn or_insert(self, value: HeaderValue) -> &'a mut HeaderValue {
+        self.or_insert_with(|| value)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> HeaderValue>(self, f: F) -> &'a mut HeaderValue {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(f()),
+        }
+    }
+
+    pub fn and_modify<F: FnOnce(&mut HeaderValue)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut e) => {
+                f(e.get_mut());
+                Entry::Occupied(e)
+            }
+            Entry::Vacant(e) => Entry::Vacant(e),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OccupiedEntry<'a> {
+    map: &'a mut HeaderMap,
+    index: usize,
+}
+
+impl<'a> OccupiedEntry<'a> {
+    pub fn name(&self) -> &HeaderName {
+        &self.map.0[self.index].0
+    }
+
+    /// The first value of the header.
+    pub fn get(&self) -> &HeaderValue {
+        &self.map.0[self.index].1
+    }
+
+    pub fn get_mut(&mut self) -> &mut HeaderValue {
+        &mut self.map.0[self.index].1
+    }
+
+    pub fn into_mut(self) -> &'a mut HeaderValue {
+        &mut self.map.0[self.index].1
+    }
+
+    /// Adds another value for the header.
+    pub fn append(&mut self, value: HeaderValue) {
+        let name = self.name().clone();
+
+        self.map.append(name, value);
+    }
+
+    /// Replaces every value of the header with `value`.
+    pub fn insert(&mut self, value: HeaderValue) {
+        let name = self.name().clone();
+
+        self.map.insert(name, value);
+    }
+
+    /// Removes every value of the header, returning the first.
+    pub fn remove(self) -> HeaderValue {
+        let (name, value) = self.map.0.remove(self.index);
+
+        self.map.0.retain(|(k, _)| *k != name);
+
+        value
+    }
+}
+
+#[derive(Debug)]
+pub struct VacantEntry<'a> {
+    map: &'a mut HeaderMap,
+    name: HeaderName,
+}
+
+impl<'a> VacantEntry<'a> {
+    pub fn name(&self) -> &HeaderName {
+        &self.name
+    }
+
+    pub fn insert(self, value: HeaderValue) -> &'a mut HeaderValue {
+        self.map.append(self.name, value);
+
+        let last = self.map.0.len() - 1;
+
+        &mut self.map.0[last].1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{header_name::HeaderName, header_value::HeaderValue};
+
+    use super::{Entry, HeaderMap};
+
+    #[test]
+    fn multiple_values_in_order() {
+        let headers = HeaderMap::from_lines(
+            "Content-Type: text/html\r\nSet-Cookie: a=1\r\nX-Id:7\r\nset-cookie: b=2".lines(),
+        );
+
+        assert_eq!(headers.get_by_str_key("x-id"), Some("7"));
+        assert_eq!(headers.get_by_str_key("SET-COOKIE"), Some("a=1"));
+        assert_eq!(
+            headers.get_all("Set-Cookie").collect::<Vec<_>>(),
+            ["a=1", "b=2"]
+        );
+
+        let mut bytes = Vec::new();
+        headers.write_to(&mut bytes).unwrap();
+
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            "Content-Type: text/html\r\nSet-Cookie: a=1\r\nX-Id: 7\r\nset-cookie: b=2\r\n"
+        );
+    }
+
+    #[test]
+    fn insert_append_remove() {
+        let mut headers = HeaderMap::new();
+
+        headers.append(HeaderName::VARY, HeaderValue::from_static("Accept"));
+        headers.insert(HeaderName::CONTENT_LENGTH, HeaderValue::from(0_usize));
+        headers.append(HeaderName::VARY, HeaderValue::from_static("Origin"));
+
+        headers.insert_by_str_key_value("VARY", "*").unwrap();
+        assert_eq!(
+            headers
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<_>>(),
+            [("Vary", "*"), ("Content-Length", "0")]
+        );
+
+        assert_eq!(headers.remove("vary").unwrap(), "*");
+        assert_eq!(headers.remove("vary"), None);
+        assert_eq!(headers.len(), 1);
+    }
+
+    #[test]
+    fn reject_injection() {
+        let mut headers = HeaderMap::new();
+
+        assert!(headers
+            .insert_by_str_key_value("Location", "/\r\nSet-Cookie: session=evil")
+            .is_err());
+        assert!(headers
+            .insert_by_str_key_value("X-Evil\r\nSet-Cookie", "1")
+            .is_err());
+        assert!(headers.is_empty());
+
+        headers.append(
+            HeaderName::AUTHORIZATION,
+            HeaderValue::from_static("Basic abc"),
+        );
+        assert!(!format!("{:?}", headers).contains("abc"));
+    }
+
+    #[test]
+    fn entries() {
+        let mut headers = HeaderMap::new();
+
+        headers
+            .entry(HeaderName::VARY)
+            .or_insert(HeaderValue::from_static("Accept"));
+        headers
+            .entry(HeaderName::VARY)
+            .and_modify(|v| *v = HeaderValue::from_static("Accept, Origin"))
+            .or_insert(HeaderValue::from_static("Origin"));
+
+        assert_eq!(headers.get_by_str_key("vary"), Some("Accept, Origin"));
+
+        if let Entry::Occupied(mut e) = headers.entry(HeaderName::VARY) {
+            e.append(HeaderValue::from_static("Cookie"));
+        }
+
+        assert_eq!(headers.get_all("vary").count(), 2);
+        assert!(matches!(headers.entry(HeaderName::ALLOW), Entry::Vacant(_)));
     }
 }
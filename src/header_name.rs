@@ -0,0 +1,247 @@
+use std::{borrow::Cow, str::FromStr};
+
+use crate::error::HttpInternalError;
+
+/// A validated header name. Names are compared case-insensitively but keep the case they were
+/// created with when written.
+#[derive(Clone)]
+pub struct HeaderName(Cow<'static, str>);
+
+macro_rules! header_name_const {
+    ($($name:ident => $value:literal),* $(,)?) => {
+        $(pub const $name: HeaderName = HeaderName::from_static($value);)*
+    };
+}
+
+impl HeaderName {
+    header_name_const! {
+        ACCEPT => "Accept",
+        ACCEPT_CHARSET => "Accept-Charset",
+        ACCEPT_ENCODING => "Accept-Encoding",
+        ACCEPT_LANGUAGE => "Accept-Language",
+        ACCEPT_RANGES => "Accept-Ranges",
+        ACCESS_CONTROL_ALLOW_CREDENTIALS => "Access-Control-Allow-Credentials",
+        ACCESS_CONTROL_ALLOW_HEADERS => "Access-Control-Allow-Headers",
+        ACCESS_CONTROL_ALLOW_METHODS => "Access-Control-Allow-Methods",
+        ACCESS_CONTROL_ALLOW_ORIGIN => "Access-Control-Allow-Origin",
+        ACCESS_CONTROL_EXPOSE_HEADERS => "Access-Control-Expose-Headers",
+        ACCESS_CONTROL_MAX_AGE => "Access-Control-Max-Age",
+        ACCESS_CONTROL_REQUEST_HEADERS => "Access-Control-Request-Headers",
+        ACCESS_CONTROL_REQUEST_METHOD => "Access-Control-Request-Method",
+        AGE => "Age",
+        ALLOW => "Allow",
+        AUTHORIZATION => "Authorization",
+        CACHE_CONTROL => "Cache-Control",
+        CONNECTION => "Connection",
+        CONTENT_DISPOSITION => "Content-Disposition",
+        CONTENT_ENCODING => "Content-Encoding",
+        CONTENT_LANGUAGE => "Content-Language",
+        CONTENT_LENGTH => "Content-Length",
+        CONTENT_LOCATION => "Content-Location",
+        CONTENT_RANGE => "Content-Range",
+        CONTENT_TYPE => "Content-Type",
+        COOKIE => "Cookie",
+        DATE => "Date",
+        ETAG => "ETag",
+        EXPECT => "Expect",
+        EXPIRES => "Expires",
+        HOST => "Host",
+        IF_MATCH => "If-Match",
+        IF_MODIFIED_SINCE => "If-Modified-Since",
+        IF_NONE_MATCH => "If-None-Match",
+        IF_RANGE => "If-Range",
+        IF_UNMODIFIED_SINCE => "If-Unmodified-Since",
+        LAST_MODIFIED => "Last-Modified",
+        LOCATION => "Location",
+        ORIGIN => "Origin",
+        PROXY_AUTHENTICATE => "Proxy-Authenticate",
+        PROXY_AUTHORIZATION => "Proxy-Authorization",
+        RANGE => "Range",
+        RATELIMIT_LIMIT => "RateLimit-Limit",
+        RATELIMIT_REMAINING => "RateLimit-Remaining",
+        RATELIMIT_RESET => "RateLimit-Reset",
+        REFERER => "Referer",
+        RETRY_AFTER => "Retry-After",
+        SERVER => "Server",
+        SET_COOKIE => "Set-Cookie",
+        TRANSFER_ENCODING => "Transfer-Encoding",
+        UPGRADE => "Upgrade",
+        USER_AGENT => "User-Agent",
+        VARY => "Vary",
+        WWW_AUTHENTICATE => "WWW-Authenticate",
+        X_FORWARDED_FOR => "X-Forwarded-For",
+    }
+
+    /// # Panics
+    ///
+    /// Panics, at compile time in a `const`, if `name` is not a valid header name.
+    pub const fn from_static(name: &'static str) -> Self {
+        assert!(is_valid_name(name.as_bytes()), "Invalid header name.");
+
+        Self(Cow::Borrowed(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether values of this header are credentials, which are redacted from `Debug` output.
+    pub fn is_sensitive(&self) -> bool {
+        [
+            HeaderName::AUTHORIZATION,
+            HeaderName::PROXY_AUTHORIZATION,
+            HeaderName::COOKIE,
+            HeaderName::SET_COOKIE,
+        ]
+        .contains(self)
+    }
+}
+
+/// Whether `name` is a non-empty token as defined by RFC 9110.
+const fn is_valid_name(name: &[u8]) -> bool {
+    if name.is_empty() {
+        return false;
+    }
+
+    let mut i = 0;
+
+    while i < name.len() {
+        let b = name[i];
+
+        let is_tchar = b.is_ascii_alphanumeric()
+            || matches!(
+                b,
+                b'!' | b'#'
+                    | b'$'
+                    | b'%'
+                    | b'&'
+                    | b'\''
+                    | b'*'
+                    | b'+'
+                    | b'-'
+                    | b'.'
+                    | b'^'
+                    | b'_'
+                    | b'`'
+                    | b'|'
+                    | b'~'
+            );
+
+        if !is_tchar {
+            return false;
+        }
+
+        i += 1;
+    }
+
+    true
+}
+
+impl FromStr for HeaderName {
+    type Err = HttpInternalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if is_valid_name(s.as_bytes()) {
+            Ok(Self(Cow::Owned(s.to_owned())))
+        } else {
+            Err(HttpInternalError::new(format!(
+                "Invalid header name '{}'.",
+                s.escape_debug()
+            )))
+        }
+    }
+}
+
+impl TryFrom<&str> for HeaderName {
+    type Error = HttpInternalError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl TryFrom<String> for HeaderName {
+    type Error = HttpInternalError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        if is_valid_name(s.as_bytes()) {
+            Ok(Self(Cow::Owned(s)))
+        } else {
+            Err(HttpInternalError::new(format!(
+                "Invalid header name '{}'.",
+                s.escape_debug()
+            )))
+        }
+    }
+}
+
+impl PartialEq for HeaderName {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_ignore_ascii_case(&other.0)
+    }
+}
+
+impl Eq for HeaderName {}
+
+impl PartialEq<str> for HeaderName {
+    fn eq(&self, other: &str) -> bool {
+        self.0.eq_ignore_ascii_case(other)
+    }
+}
+
+impl PartialEq<&str> for HeaderName {
+    fn eq(&self, other: &&str) -> bool {
+        self.0.eq_ignore_ascii_case(other)
+    }
+}
+
+impl std::hash::Hash for HeaderName {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        for b in self.0.bytes() {
+            state.write_u8(b.to_ascii_lowercase());
+        }
+    }
+}
+
+impl AsRef<str> for HeaderName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for HeaderName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl std::fmt::Display for HeaderName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HeaderName;
+
+    #[test]
+    fn validate_names() {
+        let name: HeaderName = "x-request-id".parse().unwrap();
+
+        assert_eq!(name, HeaderName::from_static("X-Request-Id"));
+        assert_eq!(HeaderName::CONTENT_TYPE, "content-type");
+        assert_eq!(name.to_string(), "x-request-id");
+        assert!(HeaderName::COOKIE.is_sensitive());
+
+        for invalid in [
+            "",
+            "X Forwarded",
+            "X-Evil\r\nSet-Cookie",
+            "Name:",
+            "Ünicode",
+        ] {
+            assert!(invalid.parse::<HeaderName>().is_err(), "{:?}", invalid);
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::{borrow::Cow, str::FromStr};
+
+use crate::{error::HttpInternalError, http_date::HttpDate, mime::Mime};
+
+/// A validated header value, which cannot contain CR, LF or other control characters apart from
+/// horizontal tab.
+///
+/// Sensitive values, such as credentials, are redacted from `Debug` output.
+#[derive(Clone)]
+pub struct HeaderValue {
+    value: Cow<'static, str>,
+    sensitive: bool,
+}
+
+impl HeaderValue {
+    /// # Panics
+    ///
+    /// Panics, at compile time in a `const`, if `value` is not a valid header value.
+    pub const fn from_static(value: &'static str) -> Self {
+        assert!(is_valid_value(value.as_bytes()), "Invalid header value.");
+
+        Self {
+            value: Cow::Borrowed(value),
+            sensitive: false,
+        }
+    }
+
+    /// For values built by this crate from types that cannot contain control characters.
+    pub(crate) fn from_trusted(value: String) -> Self {
+        debug_assert!(is_valid_value(value.as_bytes()), "Invalid header value.");
+
+        Self {
+            value: Cow::Owned(value),
+            sensitive: false,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+
+    pub fn is_sensitive(&self) -> bool {
+        self.sensitive
+    }
+
+    pub fn set_sensitive(&mut self, sensitive: bool) {
+        self.sensitive = sensitive;
+    }
+
+    pub fn sensitive(mut self) -> Self {
+        self.sensitive = true;
+        self
+    }
+}
+
+const fn is_valid_value(value: &[u8]) -> bool {
+    let mut i = 0;
+
+    while i < value.len() {
+        let b = value[i];
+
+        if !(b == b'\t' || (b >= 0x20 && b != 0x7f)) {
+            return false;
+        }
+
+        i += 1;
+    }
+
+    true
+}
+
+impl FromStr for HeaderValue {
+    type Err = HttpInternalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s.to_owned())
+    }
+}
+
+impl TryFrom<&str> for HeaderValue {
+    type Error = HttpInternalError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl TryFrom<String> for HeaderValue {
+    type Error = HttpInternalError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        if is_valid_value(s.as_bytes()) {
+            Ok(Self {
+                value: Cow::Owned(s),
+                sensitive: false,
+            })
+        } else {
+            Err(HttpInternalError::new(format!(
+                "Invalid header value '{}'.",
+                s.escape_debug()
+            )))
+        }
+    }
+}
+
+macro_rules! header_value_from_integer {
+    ($($t:ty),*) => {
+        $(impl From<$t> for HeaderValue {
+            fn from(n: $t) -> Self {
+                Self::from_trusted(n.to_string())
+            }
+        })*
+    };
+}
+
+header_value_from_integer!(u16, u32, u64, usize, i32, i64);
+
+impl From<HttpDate> for HeaderValue {
+    fn from(date: HttpDate) -> Self {
+        Self::from_trusted(date.to_string())
+    }
+}
+
+/// A `Mime` is either one of its constants or parsed by `Mime::from_str`, which rejects every
+/// control character, so it is always a valid header value.
+impl From<Mime> for HeaderValue {
+    fn from(mime: Mime) -> Self {
+        Self::from_trusted(mime.as_str().to_owned())
+    }
+}
+
+impl PartialEq for HeaderValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for HeaderValue {}
+
+impl PartialEq<str> for HeaderValue {
+    fn eq(&self, other: &str) -> bool {
+        self.value == other
+    }
+}
+
+impl PartialEq<&str> for HeaderValue {
+    fn eq(&self, other: &&str) -> bool {
+        self.value == *other
+    }
+}
+
+impl AsRef<str> for HeaderValue {
+    fn as_ref(&self) -> &str {
+        &self.value
+    }
+}
+
+impl std::fmt::Debug for HeaderValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.sensitive {
+            write!(f, "Sensitive")
+        } else {
+            write!(f, "{:?}", self.value)
+        }
+    }
+}
+
+impl std::fmt::Display for HeaderValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HeaderValue;
+
+    #[test]
+    fn validate_values() {
+        assert_eq!(
+            "text/html; q=0.9\tx".parse::<HeaderValue>().unwrap(),
+            "text/html; q=0.9\tx"
+        );
+        assert_eq!(HeaderValue::from(42_usize), "42");
+
+        for invalid in ["a\r\nSet-Cookie: x=1", "a\nb", "a\0b", "a\x7fb"] {
+            assert!(invalid.parse::<HeaderValue>().is_err(), "{:?}", invalid);
+        }
+
+        let token = HeaderValue::from_static("Bearer secret").sensitive();
+
+        assert_eq!(format!("{:?}", token), "Sensitive");
+        assert_eq!(token.to_string(), "Bearer secret");
+    }
+}
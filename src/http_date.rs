@@ -0,0 +1,336 @@
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::error::HttpInternalError;
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+
+const LONG_DAYS: [&str; 7] = [
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// A timestamp with one second precision, as used in the `Date`, `Last-Modified` and `Expires`
+/// headers.
+///
+/// Formats as an IMF-fixdate such as `Sun, 06 Nov 1994 08:49:37 GMT` and also parses the obsolete
+/// RFC 850 and asctime formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HttpDate(u64);
+
+impl HttpDate {
+    pub fn now() -> Self {
+        Self::from(SystemTime::now())
+    }
+
+    pub fn from_unix_secs(secs: u64) -> Self {
+        Self(secs)
+    }
+
+    pub fn unix_secs(&self) -> u64 {
+        self.0
+    }
+
+    /// The date and time of day as `(year, month, day, hour, minute, second)`.
+    fn parts(&self) -> (i64, u32, u32, u64, u64, u64) {
+        let secs_of_day = self.0 % 86400;
+
+        let (year, month, day) = civil_from_days((self.0 / 86400) as i64);
+
+        (
+            year,
+            month,
+            day,
+            secs_of_day / 3600,
+            secs_of_day % 3600 / 60,
+            secs_of_day % 60,
+        )
+    }
+
+    /// Formats as in the Common Log Format, such as `06/Nov/1994:08:49:37 +0000`.
+    pub(crate) fn to_common_log(self) -> String {
+        let (year, month, day, hour, minute, second) = self.parts();
+
+        format!(
+            "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+            day,
+            MONTHS[month as usize - 1],
+            year,
+            hour,
+            minute,
+            second
+        )
+    }
+
+    /// Formats as RFC 3339, such as `1994-11-06T08:49:37Z`.
+    pub(crate) fn to_rfc3339(self) -> String {
+        let (year, month, day, hour, minute, second) = self.parts();
+
+        format!(
+            "{}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year, month, day, hour, minute, second
+        )
+    }
+}
+
+impl From<SystemTime> for HttpDate {
+    fn from(time: SystemTime) -> Self {
+        Self(
+            time.duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+impl From<HttpDate> for SystemTime {
+    fn from(date: HttpDate) -> Self {
+        UNIX_EPOCH + Duration::from_secs(date.0)
+    }
+}
+
+impl std::fmt::Display for HttpDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day, hour, minute, second) = self.parts();
+
+        write!(
+            f,
+            "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+            DAYS[(self.0 / 86400 % 7) as usize],
+            day,
+            MONTHS[month as usize - 1],
+            year,
+            hour,
+            minute,
+            second
+        )
+    }
+}
+
+impl FromStr for HttpDate {
+    type Err = HttpInternalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        parse_imf_fixdate(s)
+            .or_else(|| parse_rfc850_date(s))
+            .or_else(|| parse_asctime_date(s))
+            .ok_or_else(|| HttpInternalError::new(format!("Invalid HTTP date '{}'.", s)))
+    }
+}
+
+/// Parses `Sun, 06 Nov 1994 08:49:37 GMT`.
+fn parse_imf_fixdate(s: &str) -> Option<HttpDate> {
+    let (day_name, rest) = s.split_once(", ")?;
+
+    if !DAYS.contains(&day_name) {
+        return None;
+    }
+
+    let mut parts = rest.split(' ');
+
+    let day = parse_fixed::<u32>(parts.next()?, 2)?;
+    let month = parse_month(parts.next()?)?;
+    let year = parse_fixed::<i64>(parts.next()?, 4)?;
+    let secs_of_day = parse_time(parts.next()?)?;
+
+    if parts.next()? != "GMT" || parts.next().is_some() {
+        return None;
+    }
+
+    to_http_date(year, month, day, secs_of_day)
+}
+
+/// Parses `Sunday, 06-Nov-94 08:49:37 GMT`.
+fn parse_rfc850_date(s: &str) -> Option<HttpDate> {
+    let (day_name, rest) = s.split_once(", ")?;
+
+    if !LONG_DAYS.contains(&day_name) {
+        return None;
+    }
+
+    let mut parts = rest.split(' ');
+
+    let mut date = parts.next()?.split('-');
+
+    let day = parse_fixed::<u32>(date.next()?, 2)?;
+    let month = parse_month(date.next()?)?;
+    let year = parse_fixed::<i64>(date.next()?, 2)?;
+    let secs_of_day = parse_time(parts.next()?)?;
+
+    if date.next().is_some() || parts.next()? != "GMT" || parts.next().is_some() {
+        return None;
+    }
+
+    // A two digit year more than 50 years in the future is in the previous century.
+    let (current_year, _, _) = civil_from_days((HttpDate::now().0 / 86400) as i64);
+    let century = current_year - current_year % 100;
+
+    let year = if century + year > current_year + 50 {
+        century - 100 + year
+    } else {
+        century + year
+    };
+
+    to_http_date(year, month, day, secs_of_day)
+}
+
+/// Parses `Sun Nov  6 08:49:37 1994`.
+fn parse_asctime_date(s: &str) -> Option<HttpDate> {
+    let mut parts = s.split_whitespace();
+
+    if !DAYS.contains(&parts.next()?) {
+        return None;
+    }
+
+    let month = parse_month(parts.next()?)?;
+    let day = parts
+        .next()
+        .filter(|d| d.len() <= 2 && d.bytes().all(|b| b.is_ascii_digit()))?
+        .parse::<u32>()
+        .ok()?;
+    let secs_of_day = parse_time(parts.next()?)?;
+    let year = parse_fixed::<i64>(parts.next()?, 4)?;
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    to_http_date(year, month, day, secs_of_day)
+}
+
+fn parse_fixed<T: FromStr>(s: &str, len: usize) -> Option<T> {
+    if s.len() == len && s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn parse_month(s: &str) -> Option<u32> {
+    MONTHS.iter().position(|m| *m == s).map(|m| m as u32 + 1)
+}
+
+/// Parses `08:49:37` into seconds since midnight.
+fn parse_time(s: &str) -> Option<u64> {
+    let mut parts = s.split(':');
+
+    let h = parse_fixed::<u64>(parts.next()?, 2)?;
+    let m = parse_fixed::<u64>(parts.next()?, 2)?;
+    let sec = parse_fixed::<u64>(parts.next()?, 2)?;
+
+    if parts.next().is_some() || h > 23 || m > 59 || sec > 60 {
+        return None;
+    }
+
+    Some(h * 3600 + m * 60 + sec)
+}
+
+fn to_http_date(year: i64, month: u32, day: u32, secs_of_day: u64) -> Option<HttpDate> {
+    if day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+
+    u64::try_from(days)
+        .ok()
+        .map(|d| HttpDate(d * 86400 + secs_of_day))
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Conversions between days since 1970-01-01 and the proleptic Gregorian calendar, from
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::HttpDate;
+
+    #[test]
+    fn format_and_parse() {
+        let date = HttpDate::from_unix_secs(784111777);
+
+        assert_eq!(date.to_string(), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(date.to_common_log(), "06/Nov/1994:08:49:37 +0000");
+        assert_eq!(date.to_rfc3339(), "1994-11-06T08:49:37Z");
+        assert_eq!(
+            HttpDate::from_str("Sun, 06 Nov 1994 08:49:37 GMT").unwrap(),
+            date
+        );
+
+        assert_eq!(
+            HttpDate::from_unix_secs(951782400).to_string(),
+            "Tue, 29 Feb 2000 00:00:00 GMT"
+        );
+        assert_eq!(
+            HttpDate::from_str("Mon, 23 May 2005 22:38:34 GMT")
+                .unwrap()
+                .unix_secs(),
+            1116887914
+        );
+
+        assert_eq!(
+            HttpDate::from_str("Sunday, 06-Nov-94 08:49:37 GMT").unwrap(),
+            date
+        );
+        assert_eq!(
+            HttpDate::from_str("Sun Nov  6 08:49:37 1994").unwrap(),
+            date
+        );
+
+        assert!(HttpDate::from_str("Sun, 06 Nov 1994 08:49:37").is_err());
+        assert!(HttpDate::from_str("Sunday, 06-Nov-1994 08:49:37 GMT").is_err());
+        assert!(HttpDate::from_str("Sun, 31 Feb 1994 08:49:37 GMT").is_err());
+        assert!(HttpDate::from_str("Sun, 6 Nov 1994 08:49:37 GMT").is_err());
+    }
+}
@@ -38,7 +38,7 @@ pub trait HttpItem {
 
     fn as_bytes(&self) -> Result<Vec<u8>>;
 
-    fn write_to<T: Write>(&self, writer: &mut T) -> Result<()> {
+    fn write_to<T: Write>(&mut self, writer: &mut T) -> Result<()> {
         let bytes = self.as_bytes()?;
 
         writer.write_all(&bytes)?;
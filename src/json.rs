@@ -0,0 +1,150 @@
+use std::ops::Deref;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    error::HttpError,
+    extract::FromRequest,
+    http_status::HttpStatus,
+    mime::Mime,
+    request::{Request, RequestBuilder, ServerRequest},
+    response::{HttpResponse, Response, ResponseBuilder},
+    Result,
+};
+
+/// A JSON request or response body.
+///
+/// As a response it is serialized with an `application/json` content type. As an extractor it
+/// fails with `415 Unsupported Media Type` unless the request is JSON, and with
+/// `400 Bad Request` if the body cannot be deserialized into `T`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> HttpResponse for Json<T> {
+    fn into_response(self: Box<Self>) -> Response {
+        match serde_json::to_vec(&self.0) {
+            Ok(body) => ResponseBuilder::new()
+                .content_type(Mime::APPLICATION_JSON)
+                .body(body)
+                .build(),
+            Err(e) => Box::new(HttpError::internal_server_error(format!(
+                "Failed to serialize response: {}",
+                e
+            )))
+            .into_response(),
+        }
+    }
+}
+
+impl<T: DeserializeOwned> FromRequest for Json<T> {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        req.request.json().map(Json)
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+fn is_json(mime: &Mime) -> bool {
+    mime.matches(&Mime::APPLICATION_JSON) || mime.subtype().to_ascii_lowercase().ends_with("+json")
+}
+
+impl Request {
+    pub fn json<T: DeserializeOwned>(&self) -> std::result::Result<T, HttpError> {
+        if !self.content_type().is_some_and(|c| is_json(&c)) {
+            return Err(HttpError::unsupported_media_type(
+                "Expected request with 'Content-Type: application/json'.",
+            ));
+        }
+
+        serde_json::from_slice(&self.body.contents).map_err(|e| {
+            HttpError::new(
+                format!("Failed to deserialize the JSON body: {}", e),
+                HttpStatus::BadRequest,
+            )
+        })
+    }
+}
+
+impl RequestBuilder {
+    /// Serializes `value` as the request body with an `application/json` content type.
+    pub fn json<T: Serialize>(self, value: &T) -> Result<Self> {
+        let body = serde_json::to_vec(value)?;
+
+        Ok(self.content_type(Mime::APPLICATION_JSON).body(body))
+    }
+}
+
+impl Response {
+    pub fn json<T: DeserializeOwned>(self) -> Result<T> {
+        let res = serde_json::from_slice(&self.body.contents)?;
+
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, net::SocketAddr};
+
+    use crate::{
+        extract::FromRequest,
+        header_item::HeaderItem,
+        http_status::HttpStatus,
+        request::{RequestBuilder, ServerRequest},
+        response::HttpResponse,
+        route::RouteKey,
+    };
+
+    use super::Json;
+
+    type Scores = HashMap<String, u32>;
+
+    fn server_request(builder: RequestBuilder) -> ServerRequest {
+        ServerRequest::new(
+            RouteKey("/".to_owned()),
+            builder.build(),
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+        )
+    }
+
+    #[test]
+    fn extract_json() {
+        let scores = Scores::from([("zak".to_owned(), 24)]);
+
+        let mut req = server_request(RequestBuilder::new().json(&scores).unwrap());
+        let Json(extracted) = Json::<Scores>::from_request(&mut req).unwrap();
+        assert_eq!(extracted, scores);
+
+        let mut req = server_request(
+            RequestBuilder::new()
+                .insert_header_key_val("Content-Type", "application/vnd.api+json")
+                .unwrap()
+                .body("[1, 2]"),
+        );
+        let err = Json::<Scores>::from_request(&mut req).unwrap_err();
+        assert_eq!(err.status(), HttpStatus::BadRequest);
+
+        let mut req = server_request(RequestBuilder::new().body(r#"{"zak": 24}"#));
+        let err = Json::<Scores>::from_request(&mut req).unwrap_err();
+        assert_eq!(err.status(), HttpStatus::UnsupportedMediaType);
+    }
+
+    #[test]
+    fn json_response() {
+        let scores = Scores::from([("zak".to_owned(), 24)]);
+
+        let res = Box::new(Json(scores.clone())).into_response();
+
+        assert_eq!(
+            res.header.header_map().get_by_str_key("content-type"),
+            Some("application/json")
+        );
+        assert_eq!(res.json::<Scores>().unwrap(), scores);
+    }
+}
@@ -1,18 +1,46 @@
 use error::HttpInternalError;
 
+pub mod access_log;
+pub mod auth;
+pub mod base64;
 pub mod body;
 pub mod client;
+#[cfg(feature = "compression")]
+pub mod compression;
+pub mod conditional;
+pub mod cookie;
+pub mod cors;
+mod crypto;
+pub mod deadline;
 pub mod error;
+pub mod extensions;
+pub mod extract;
+pub mod handler;
 pub mod header_item;
 pub mod header_map;
+pub mod header_name;
+pub mod header_value;
+pub mod http_date;
 pub mod http_item;
 pub mod http_status;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod method;
+pub mod middleware;
+pub mod mime;
+pub mod multipart;
+pub mod negotiation;
+pub mod params;
 pub mod pool;
+pub mod range;
+pub mod rate_limit;
 pub mod request;
 pub mod response;
 pub mod route;
 pub mod server;
+pub mod session;
+pub mod static_files;
+pub mod typed_header;
 pub mod url;
 
 type Result<T> = std::result::Result<T, HttpInternalError>;
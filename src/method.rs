@@ -2,7 +2,7 @@ use std::{fmt::Display, str::FromStr};
 
 use crate::error::HttpInternalError;
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Method {
     GET,
     HEAD,
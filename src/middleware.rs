@@ -0,0 +1,97 @@
+use crate::{request::ServerRequest, response::Response};
+
+pub trait Middleware: Send + Sync + 'static {
+    fn handle(&self, req: ServerRequest, next: Next<'_>) -> Response;
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(ServerRequest, Next<'_>) -> Response + Send + Sync + 'static,
+{
+    fn handle(&self, req: ServerRequest, next: Next<'_>) -> Response {
+        (self)(req, next)
+    }
+}
+
+/// The remainder of the middleware chain, ending in the route handler.
+pub struct Next<'a> {
+    middleware: &'a [Box<dyn Middleware>],
+    endpoint: &'a dyn Fn(ServerRequest) -> Response,
+}
+
+impl std::fmt::Debug for Next<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Next")
+            .field("remaining", &self.middleware.len())
+            .finish()
+    }
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        middleware: &'a [Box<dyn Middleware>],
+        endpoint: &'a dyn Fn(ServerRequest) -> Response,
+    ) -> Self {
+        Self {
+            middleware,
+            endpoint,
+        }
+    }
+
+    pub fn run(self, req: ServerRequest) -> Response {
+        if let Some((current, rest)) = self.middleware.split_first() {
+            current.handle(req, Next::new(rest, self.endpoint))
+        } else {
+            (self.endpoint)(req)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use crate::{
+        request::{RequestBuilder, ServerRequest},
+        response::{Response, ResponseBuilder},
+        route::RouteKey,
+    };
+
+    use super::{Middleware, Next};
+
+    #[derive(Debug)]
+    struct Visited(Vec<&'static str>);
+
+    #[test]
+    fn run_chain_in_order() {
+        let first = |mut req: ServerRequest, next: Next<'_>| {
+            req.extensions_mut().insert(Visited(vec!["first"]));
+            next.run(req)
+        };
+
+        let second = |mut req: ServerRequest, next: Next<'_>| {
+            if let Some(v) = req.extensions_mut().get_mut::<Visited>() {
+                v.0.push("second");
+            }
+            next.run(req)
+        };
+
+        let middleware: Vec<Box<dyn Middleware>> = vec![Box::new(first), Box::new(second)];
+
+        let endpoint = |req: ServerRequest| -> Response {
+            let visited = req.extensions().get::<Visited>().unwrap().0.join(",");
+
+            ResponseBuilder::new().body(visited).build()
+        };
+
+        let req = ServerRequest::new(
+            RouteKey("/".to_owned()),
+            RequestBuilder::new().build(),
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+        );
+
+        let res = Next::new(&middleware, &endpoint).run(req);
+
+        assert_eq!(res.body.contents, b"first,second");
+    }
+}
@@ -0,0 +1,204 @@
+use std::{borrow::Cow, str::FromStr};
+
+use crate::error::HttpInternalError;
+
+/// A media type such as `text/html; charset=utf-8`, as used in the `Content-Type` header.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Mime(Cow<'static, str>);
+
+macro_rules! mime_const {
+    ($name:ident, $value:literal) => {
+        pub const $name: Mime = Mime(Cow::Borrowed($value));
+    };
+}
+
+impl Mime {
+    mime_const!(TEXT_PLAIN, "text/plain");
+    mime_const!(TEXT_PLAIN_UTF_8, "text/plain; charset=utf-8");
+    mime_const!(TEXT_HTML, "text/html");
+    mime_const!(TEXT_HTML_UTF_8, "text/html; charset=utf-8");
+    mime_const!(TEXT_CSS, "text/css");
+    mime_const!(TEXT_CSV, "text/csv");
+    mime_const!(TEXT_JAVASCRIPT, "text/javascript");
+    mime_const!(APPLICATION_JSON, "application/json");
+    mime_const!(APPLICATION_PROBLEM_JSON, "application/problem+json");
+    mime_const!(APPLICATION_XML, "application/xml");
+    mime_const!(APPLICATION_OCTET_STREAM, "application/octet-stream");
+    mime_const!(
+        APPLICATION_WWW_FORM_URLENCODED,
+        "application/x-www-form-urlencoded"
+    );
+    mime_const!(APPLICATION_PDF, "application/pdf");
+    mime_const!(MULTIPART_FORM_DATA, "multipart/form-data");
+    mime_const!(MULTIPART_BYTERANGES, "multipart/byteranges");
+    mime_const!(IMAGE_PNG, "image/png");
+    mime_const!(IMAGE_JPEG, "image/jpeg");
+    mime_const!(IMAGE_GIF, "image/gif");
+    mime_const!(IMAGE_SVG, "image/svg+xml");
+    mime_const!(IMAGE_WEBP, "image/webp");
+    mime_const!(IMAGE_ICON, "image/x-icon");
+    mime_const!(APPLICATION_WASM, "application/wasm");
+    mime_const!(APPLICATION_ZIP, "application/zip");
+    mime_const!(APPLICATION_GZIP, "application/gzip");
+    mime_const!(FONT_WOFF, "font/woff");
+    mime_const!(FONT_WOFF2, "font/woff2");
+    mime_const!(FONT_TTF, "font/ttf");
+    mime_const!(FONT_OTF, "font/otf");
+    mime_const!(AUDIO_MPEG, "audio/mpeg");
+    mime_const!(AUDIO_WAV, "audio/wav");
+    mime_const!(AUDIO_OGG, "audio/ogg");
+    mime_const!(VIDEO_MP4, "video/mp4");
+    mime_const!(VIDEO_WEBM, "video/webm");
+
+    /// Guesses the media type from a file extension, falling back to
+    /// `application/octet-stream`. Text types include a UTF-8 charset.
+    pub fn from_extension(ext: &str) -> Mime {
+        match ext.to_ascii_lowercase().as_str() {
+            "html" | "htm" => Mime::TEXT_HTML_UTF_8,
+            "css" => Mime(Cow::Borrowed("text/css; charset=utf-8")),
+            "js" | "mjs" => Mime(Cow::Borrowed("text/javascript; charset=utf-8")),
+            "txt" => Mime::TEXT_PLAIN_UTF_8,
+            "csv" => Mime(Cow::Borrowed("text/csv; charset=utf-8")),
+            "json" | "map" => Mime::APPLICATION_JSON,
+            "xml" => Mime::APPLICATION_XML,
+            "pdf" => Mime::APPLICATION_PDF,
+            "wasm" => Mime::APPLICATION_WASM,
+            "zip" => Mime::APPLICATION_ZIP,
+            "gz" => Mime::APPLICATION_GZIP,
+            "png" => Mime::IMAGE_PNG,
+            "jpg" | "jpeg" => Mime::IMAGE_JPEG,
+            "gif" => Mime::IMAGE_GIF,
+            "svg" => Mime::IMAGE_SVG,
+            "webp" => Mime::IMAGE_WEBP,
+            "ico" => Mime::IMAGE_ICON,
+            "woff" => Mime::FONT_WOFF,
+            "woff2" => Mime::FONT_WOFF2,
+            "ttf" => Mime::FONT_TTF,
+            "otf" => Mime::FONT_OTF,
+            "mp3" => Mime::AUDIO_MPEG,
+            "wav" => Mime::AUDIO_WAV,
+            "ogg" => Mime::AUDIO_OGG,
+            "mp4" => Mime::VIDEO_MP4,
+            "webm" => Mime::VIDEO_WEBM,
+            _ => Mime::APPLICATION_OCTET_STREAM,
+        }
+    }
+
+    pub fn from_path<P: AsRef<std::path::Path>>(path: P) -> Mime {
+        path.as_ref()
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(Mime::from_extension)
+            .unwrap_or(Mime::APPLICATION_OCTET_STREAM)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The `type/subtype` without any parameters.
+    pub fn essence(&self) -> &str {
+        self.0.split(';').next().unwrap_or_default().trim()
+    }
+
+    pub fn type_(&self) -> &str {
+        self.essence().split('/').next().unwrap_or_default()
+    }
+
+    pub fn subtype(&self) -> &str {
+        self.essence().split('/').nth(1).unwrap_or_default()
+    }
+
+    /// The value of the parameter `name`, with any surrounding quotes removed.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.0
+            .split(';')
+            .skip(1)
+            .filter_map(|p| p.split_once('='))
+            .find(|(k, _)| k.trim().eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim().trim_matches('"'))
+    }
+
+    /// Whether `self` has the same `type/subtype` as `other`, ignoring case and parameters.
+    pub fn matches(&self, other: &Mime) -> bool {
+        self.essence().eq_ignore_ascii_case(other.essence())
+    }
+}
+
+impl FromStr for Mime {
+    type Err = HttpInternalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        let essence = s.split(';').next().unwrap_or_default().trim();
+
+        let valid = essence
+            .split_once('/')
+            .map(|(t, st)| {
+                let is_token = |p: &str| {
+                    !p.is_empty()
+                        && p.bytes()
+                            .all(|b| b.is_ascii_alphanumeric() || b"!#$&-^_.+".contains(&b))
+                };
+
+                is_token(t) && is_token(st)
+            })
+            .unwrap_or(false);
+
+        if valid && !s.bytes().any(|b| b.is_ascii_control()) {
+            Ok(Mime(Cow::Owned(s.to_owned())))
+        } else {
+            Err(HttpInternalError::new(format!(
+                "Invalid media type '{}'.",
+                s
+            )))
+        }
+    }
+}
+
+impl std::fmt::Display for Mime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl AsRef<str> for Mime {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::Mime;
+
+    #[test]
+    fn parse_mime() {
+        let mime = Mime::from_str("Text/HTML; Charset=\"UTF-8\"").unwrap();
+
+        assert_eq!(mime.essence(), "Text/HTML");
+        assert_eq!(mime.type_(), "Text");
+        assert_eq!(mime.subtype(), "HTML");
+        assert_eq!(mime.param("charset"), Some("UTF-8"));
+        assert!(mime.matches(&Mime::TEXT_HTML_UTF_8));
+        assert!(!mime.matches(&Mime::TEXT_PLAIN));
+
+        assert!(Mime::from_str("text").is_err());
+        assert!(Mime::from_str("text/").is_err());
+        assert!(Mime::from_str("text/plain\r\nX-Evil: 1").is_err());
+        assert!(Mime::from_str("text/plain; a=\u{0}").is_err());
+        assert!(Mime::from_str("text/plain; a=\tb").is_err());
+        assert!(Mime::from_str("text/plain; a=\u{7f}").is_err());
+    }
+
+    #[test]
+    fn guess_mime() {
+        assert_eq!(Mime::from_path("site/index.HTML"), Mime::TEXT_HTML_UTF_8);
+        assert_eq!(Mime::from_path("app.min.js").essence(), "text/javascript");
+        assert_eq!(Mime::from_path("logo.png"), Mime::IMAGE_PNG);
+        assert_eq!(Mime::from_path("README"), Mime::APPLICATION_OCTET_STREAM);
+    }
+}
@@ -0,0 +1,814 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Cursor, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    error::HttpError,
+    extract::FromRequest,
+    header_map::HeaderMap,
+    http_status::HttpStatus,
+    mime::Mime,
+    params::percent_decode,
+    request::{Request, RequestBuilder, ServerRequest},
+};
+
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+const READ_CHUNK_SIZE: usize = 8 * 1024;
+
+/// Limits on a multipart body, so that clients cannot fill memory or disk.
+///
+/// By default a body can be up to 64 MiB with at most 100 parts of up to 32 MiB each. Set a
+/// limit to `None` to remove it.
+#[derive(Debug, Clone, Copy)]
+pub struct MultipartLimits {
+    /// Parts larger than this are written to a temporary file instead of kept in memory.
+    pub memory_threshold: usize,
+    pub max_part_size: Option<u64>,
+    pub max_total_size: Option<u64>,
+    pub max_parts: Option<usize>,
+    pub max_header_size: usize,
+}
+
+impl std::default::Default for MultipartLimits {
+    fn default() -> Self {
+        Self {
+            memory_threshold: 1024 * 1024,
+            max_part_size: Some(32 * 1024 * 1024),
+            max_total_size: Some(64 * 1024 * 1024),
+            max_parts: Some(100),
+            max_header_size: 8 * 1024,
+        }
+    }
+}
+
+/// A `multipart/form-data` parser reading one part at a time from `R`.
+#[derive(Debug)]
+pub struct Multipart<R> {
+    reader: R,
+    boundary: Vec<u8>,
+    limits: MultipartLimits,
+    buf: Vec<u8>,
+    eof: bool,
+    started: bool,
+    finished: bool,
+    parts_read: usize,
+    total_read: u64,
+}
+
+impl<R: Read> Multipart<R> {
+    pub fn new(reader: R, boundary: &str) -> Self {
+        Self {
+            reader,
+            boundary: boundary.as_bytes().to_vec(),
+            limits: MultipartLimits::default(),
+            buf: Vec::new(),
+            eof: false,
+            started: false,
+            finished: false,
+            parts_read: 0,
+            total_read: 0,
+        }
+    }
+
+    pub fn limits(mut self, limits: MultipartLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Reads the next part, returning `None` once the closing boundary has been read.
+    pub fn next_part(&mut self) -> std::result::Result<Option<Part>, HttpError> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        if !self.started {
+            self.skip_preamble()?;
+            self.started = true;
+
+            if self.finished {
+                return Ok(None);
+            }
+        }
+
+        self.parts_read += 1;
+
+        if self
+            .limits
+            .max_parts
+            .is_some_and(|max| self.parts_read > max)
+        {
+            return Err(HttpError::payload_too_large(
+                "Multipart body has too many parts.",
+            ));
+        }
+
+        let headers = self.read_part_headers()?;
+
+        let disposition = headers
+            .get_by_str_key("content-disposition")
+            .unwrap_or_default();
+
+        let name = disposition_param(disposition, "name");
+
+        let filename = disposition_param(disposition, "filename*")
+            .and_then(|f| {
+                f.split_once("''")
+                    .map(|(_, encoded)| percent_decode(encoded, false))
+            })
+            .or_else(|| disposition_param(disposition, "filename"));
+
+        let content_type = headers
+            .get_by_str_key("content-type")
+            .and_then(|c| c.parse().ok());
+
+        let data = self.read_part_data()?;
+
+        Ok(Some(Part {
+            headers,
+            name,
+            filename,
+            content_type,
+            data,
+        }))
+    }
+
+    fn fill(&mut self) -> std::result::Result<usize, HttpError> {
+        if self.eof {
+            return Ok(0);
+        }
+
+        let mut chunk = [0; READ_CHUNK_SIZE];
+
+        let r = self.reader.read(&mut chunk).map_err(io_error)?;
+
+        if r == 0 {
+            self.eof = true;
+        }
+
+        self.total_read += r as u64;
+
+        if self
+            .limits
+            .max_total_size
+            .is_some_and(|max| self.total_read > max)
+        {
+            return Err(HttpError::payload_too_large("Multipart body is too large."));
+        }
+
+        self.buf.extend_from_slice(&chunk[..r]);
+
+        Ok(r)
+    }
+
+    fn read_line(&mut self, max_len: usize) -> std::result::Result<Vec<u8>, HttpError> {
+        loop {
+            if let Some(i) = find(&self.buf, b"\r\n") {
+                let mut line: Vec<u8> = self.buf.drain(..i + 2).collect();
+                line.truncate(i);
+
+                return Ok(line);
+            }
+
+            if self.buf.len() > max_len {
+                return Err(HttpError::new(
+                    "Multipart header line is too long.",
+                    HttpStatus::RequestHeaderFieldsTooLarge,
+                ));
+            }
+
+            if self.fill()? == 0 {
+                return Err(unexpected_end());
+            }
+        }
+    }
+
+    fn skip_preamble(&mut self) -> std::result::Result<(), HttpError> {
+        let mut delimiter = b"--".to_vec();
+        delimiter.extend_from_slice(&self.boundary);
+
+        loop {
+            let line = self.read_line(self.limits.max_header_size)?;
+
+            if let Some(rest) = line.strip_prefix(delimiter.as_slice()) {
+                if rest.starts_with(b"--") {
+                    self.finished = true;
+                }
+
+                if rest.trim_ascii().is_empty() || self.finished {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn read_part_headers(&mut self) -> std::result::Result<HeaderMap, HttpError> {
+        let mut header_str = String::new();
+
+        loop {
+            let line = self.read_line(self.limits.max_header_size)?;
+
+            if line.is_empty() {
+                break;
+            }
+
+            header_str.push_str(&String::from_utf8_lossy(&line));
+            header_str.push('\n');
+
+            if header_str.len() > self.limits.max_header_size {
+                return Err(HttpError::new(
+                    "Multipart part headers are too large.",
+                    HttpStatus::RequestHeaderFieldsTooLarge,
+                ));
+            }
+        }
+
+        Ok(HeaderMap::from_lines(header_str.lines()))
+    }
+
+    fn read_part_data(&mut self) -> std::result::Result<PartData, HttpError> {
+        let mut delimiter = b"\r\n--".to_vec();
+        delimiter.extend_from_slice(&self.boundary);
+
+        let mut sink = PartSink::new(self.limits);
+
+        loop {
+            if let Some(i) = find(&self.buf, &delimiter) {
+                sink.write(&self.buf[..i])?;
+                self.buf.drain(..i + delimiter.len());
+
+                while self.buf.len() < 2 && self.fill()? > 0 {}
+
+                // The closing delimiter does not need to be followed by a line break.
+                if self.buf.starts_with(b"--") {
+                    self.finished = true;
+                } else {
+                    self.read_line(self.limits.max_header_size)?;
+                }
+
+                return sink.finish();
+            }
+
+            let safe_len = self.buf.len().saturating_sub(delimiter.len() - 1);
+
+            sink.write(&self.buf[..safe_len])?;
+            self.buf.drain(..safe_len);
+
+            if self.fill()? == 0 {
+                return Err(unexpected_end());
+            }
+        }
+    }
+}
+
+impl Multipart<Cursor<Vec<u8>>> {
+    /// Takes the body of a `multipart/form-data` request, failing with
+    /// `415 Unsupported Media Type` if the request has a different content type or no boundary.
+    ///
+    /// The server reads the whole request body into memory before handlers run, so spilling
+    /// parts to disk does not lower the memory used by an upload here. It only avoids holding a
+    /// second copy of large parts.
+    pub fn from_request(req: &mut Request) -> std::result::Result<Self, HttpError> {
+        let boundary = req
+            .content_type()
+            .filter(|c| c.matches(&Mime::MULTIPART_FORM_DATA))
+            .and_then(|c| c.param("boundary").map(|b| b.to_owned()))
+            .ok_or_else(|| {
+                HttpError::unsupported_media_type(
+                    "Expected request with 'Content-Type: multipart/form-data' and a boundary.",
+                )
+            })?;
+
+        let body = std::mem::take(&mut req.body.contents);
+
+        Ok(Self::new(Cursor::new(body), &boundary))
+    }
+}
+
+impl FromRequest for Multipart<Cursor<Vec<u8>>> {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        Self::from_request(&mut req.request)
+    }
+}
+
+#[derive(Debug)]
+pub struct Part {
+    headers: HeaderMap,
+    name: Option<String>,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+    data: PartData,
+}
+
+#[derive(Debug)]
+pub enum PartData {
+    Memory(Vec<u8>),
+    File(TempFile),
+}
+
+impl Part {
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The field name from the `Content-Disposition` header.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    pub fn data(&self) -> &PartData {
+        &self.data
+    }
+
+    pub fn len(&self) -> u64 {
+        match &self.data {
+            PartData::Memory(b) => b.len() as u64,
+            PartData::File(f) => f.len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn reader(&self) -> std::io::Result<Box<dyn Read + '_>> {
+        match &self.data {
+            PartData::Memory(b) => Ok(Box::new(b.as_slice())),
+            PartData::File(f) => Ok(Box::new(File::open(&f.path)?)),
+        }
+    }
+
+    pub fn bytes(self) -> std::io::Result<Vec<u8>> {
+        match self.data {
+            PartData::Memory(b) => Ok(b),
+            PartData::File(f) => std::fs::read(&f.path),
+        }
+    }
+
+    pub fn text(self) -> std::result::Result<String, HttpError> {
+        let bytes = self.bytes().map_err(io_error)?;
+
+        String::from_utf8(bytes)
+            .map_err(|_| HttpError::bad_request("Multipart part is not valid UTF-8."))
+    }
+}
+
+/// A part spilled to disk. The file is deleted when this is dropped unless it has been persisted.
+#[derive(Debug)]
+pub struct TempFile {
+    path: PathBuf,
+    len: u64,
+    persisted: bool,
+}
+
+impl TempFile {
+    fn create() -> std::io::Result<(Self, File)> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+
+        let path = std::env::temp_dir().join(format!(
+            "http_lib2-{}-{}-{}.part",
+            std::process::id(),
+            nanos,
+            TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+
+        // Uploads may be private, and the temporary directory is shared with other users.
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let file = options.open(&path)?;
+
+        Ok((
+            Self {
+                path,
+                len: 0,
+                persisted: false,
+            },
+            file,
+        ))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Moves the file to `to`, falling back to a copy if it is on another filesystem.
+    pub fn persist<P: AsRef<Path>>(mut self, to: P) -> std::io::Result<()> {
+        if std::fs::rename(&self.path, to.as_ref()).is_err() {
+            std::fs::copy(&self.path, to.as_ref())?;
+            std::fs::remove_file(&self.path)?;
+        }
+
+        self.persisted = true;
+
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+struct PartSink {
+    limits: MultipartLimits,
+    memory: Vec<u8>,
+    file: Option<(TempFile, File)>,
+    len: u64,
+}
+
+impl PartSink {
+    fn new(limits: MultipartLimits) -> Self {
+        Self {
+            limits,
+            memory: Vec::new(),
+            file: None,
+            len: 0,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> std::result::Result<(), HttpError> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        self.len += bytes.len() as u64;
+
+        if self.limits.max_part_size.is_some_and(|max| self.len > max) {
+            return Err(HttpError::payload_too_large("Multipart part is too large."));
+        }
+
+        if self.file.is_none() && self.memory.len() + bytes.len() > self.limits.memory_threshold {
+            let (temp, mut file) = TempFile::create().map_err(io_error)?;
+
+            file.write_all(&self.memory).map_err(io_error)?;
+            self.memory = Vec::new();
+            self.file = Some((temp, file));
+        }
+
+        if let Some((_, file)) = &mut self.file {
+            file.write_all(bytes).map_err(io_error)
+        } else {
+            self.memory.extend_from_slice(bytes);
+            Ok(())
+        }
+    }
+
+    fn finish(self) -> std::result::Result<PartData, HttpError> {
+        if let Some((mut temp, mut file)) = self.file {
+            file.flush().map_err(io_error)?;
+            temp.len = self.len;
+
+            Ok(PartData::File(temp))
+        } else {
+            Ok(PartData::Memory(self.memory))
+        }
+    }
+}
+
+/// Builds a `multipart/form-data` request body.
+#[derive(Debug)]
+pub struct MultipartBuilder {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl std::default::Default for MultipartBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultipartBuilder {
+    pub fn new() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+
+        let boundary = format!(
+            "----http_lib2-{:x}-{:x}",
+            nanos,
+            TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+
+        Self {
+            boundary,
+            body: Vec::new(),
+        }
+    }
+
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    pub fn content_type(&self) -> Mime {
+        format!("multipart/form-data; boundary={}", self.boundary)
+            .parse()
+            .unwrap_or(Mime::MULTIPART_FORM_DATA)
+    }
+
+    pub fn text(mut self, name: &str, value: &str) -> Self {
+        self.write_part_headers(name, None, None);
+        self.body.extend_from_slice(value.as_bytes());
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+
+    pub fn file<T: AsRef<[u8]>>(
+        mut self,
+        name: &str,
+        filename: &str,
+        content_type: Mime,
+        contents: T,
+    ) -> Self {
+        self.write_part_headers(name, Some(filename), Some(&content_type));
+        self.body.extend_from_slice(contents.as_ref());
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+
+    fn write_part_headers(
+        &mut self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+    ) {
+        let quote = |s: &str| {
+            s.replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace(['\r', '\n'], " ")
+        };
+
+        self.body
+            .extend_from_slice(format!("--{}\r\n", self.boundary).as_bytes());
+
+        let mut disposition = format!("Content-Disposition: form-data; name=\"{}\"", quote(name));
+
+        if let Some(filename) = filename {
+            disposition.push_str(&format!("; filename=\"{}\"", quote(filename)));
+        }
+
+        self.body.extend_from_slice(disposition.as_bytes());
+        self.body.extend_from_slice(b"\r\n");
+
+        if let Some(content_type) = content_type {
+            self.body
+                .extend_from_slice(format!("Content-Type: {}\r\n", content_type).as_bytes());
+        }
+
+        self.body.extend_from_slice(b"\r\n");
+    }
+
+    pub fn build(mut self) -> Vec<u8> {
+        self.body
+            .extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        self.body
+    }
+}
+
+impl RequestBuilder {
+    pub fn multipart(self, multipart: MultipartBuilder) -> Self {
+        let content_type = multipart.content_type();
+
+        self.content_type(content_type).body(multipart.build())
+    }
+}
+
+/// Reads a parameter such as `name="field"` from a `Content-Disposition` value.
+fn disposition_param(value: &str, param: &str) -> Option<String> {
+    let mut rest = value.split_once(';')?.1;
+
+    loop {
+        rest = rest.trim_start();
+
+        let (key, after_key) = rest.split_once('=')?;
+
+        let after_key = after_key.trim_start();
+
+        let (val, remaining) = if let Some(quoted) = after_key.strip_prefix('"') {
+            let mut val = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some((_, escaped)) = chars.next() {
+                            val.push(escaped);
+                        }
+                    }
+                    '"' => {
+                        end = i + 1;
+                        break;
+                    }
+                    c => val.push(c),
+                }
+            }
+
+            (val, &quoted[end..])
+        } else {
+            let end = after_key.find(';').unwrap_or(after_key.len());
+
+            (after_key[..end].trim().to_owned(), &after_key[end..])
+        };
+
+        if key.trim().eq_ignore_ascii_case(param) {
+            return Some(val);
+        }
+
+        rest = remaining.split_once(';')?.1;
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn unexpected_end() -> HttpError {
+    HttpError::bad_request("Unexpected end of multipart body.")
+}
+
+fn io_error(e: std::io::Error) -> HttpError {
+    HttpError::internal_server_error(format!("Failed to process multipart body: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use crate::{http_status::HttpStatus, mime::Mime, request::RequestBuilder};
+
+    use super::{disposition_param, Multipart, MultipartBuilder, MultipartLimits, PartData};
+
+    #[test]
+    fn parse_multipart() {
+        let body = "preamble\r\n\
+            --XyZ\r\n\
+            Content-Disposition: form-data; name=\"title\"\r\n\
+            \r\n\
+            Holiday\r\n\
+            --XyZ\r\n\
+            Content-Disposition: form-data; name=\"photo\"; filename=\"beach \\\"1\\\".txt\"\r\n\
+            Content-Type: text/plain\r\n\
+            \r\n\
+            line one\r\nline two\r\n\
+            --XyZ--\r\n";
+
+        let mut multipart = Multipart::new(Cursor::new(body), "XyZ");
+
+        let title = multipart.next_part().unwrap().unwrap();
+        assert_eq!(title.name(), Some("title"));
+        assert_eq!(title.filename(), None);
+        assert_eq!(title.text().unwrap(), "Holiday");
+
+        let photo = multipart.next_part().unwrap().unwrap();
+        assert_eq!(photo.name(), Some("photo"));
+        assert_eq!(photo.filename(), Some("beach \"1\".txt"));
+        assert_eq!(photo.content_type(), Some(&Mime::TEXT_PLAIN));
+        assert_eq!(photo.text().unwrap(), "line one\r\nline two");
+
+        assert!(multipart.next_part().unwrap().is_none());
+    }
+
+    #[test]
+    fn spill_to_temp_file_and_limits() {
+        let contents = vec![7_u8; 20_000];
+
+        let builder = MultipartBuilder::new()
+            .text("description", "big file")
+            .file(
+                "upload",
+                "big.bin",
+                Mime::APPLICATION_OCTET_STREAM,
+                &contents,
+            );
+
+        let mut req = RequestBuilder::new().multipart(builder).build();
+
+        let limits = MultipartLimits {
+            memory_threshold: 1024,
+            ..MultipartLimits::default()
+        };
+
+        let mut multipart = Multipart::from_request(&mut req).unwrap().limits(limits);
+
+        let description = multipart.next_part().unwrap().unwrap();
+        assert!(matches!(description.data(), PartData::Memory(_)));
+
+        let upload = multipart.next_part().unwrap().unwrap();
+        assert_eq!(upload.len(), 20_000);
+
+        let path = match upload.data() {
+            PartData::File(f) => f.path().to_owned(),
+            PartData::Memory(_) => panic!("Expected part to be spilled to disk"),
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let mut read_back = Vec::new();
+        upload
+            .reader()
+            .unwrap()
+            .read_to_end(&mut read_back)
+            .unwrap();
+        assert_eq!(read_back, contents);
+
+        drop(upload);
+        assert!(!path.exists());
+
+        let builder = MultipartBuilder::new().file("upload", "big.bin", Mime::IMAGE_PNG, &contents);
+        let mut req = RequestBuilder::new().multipart(builder).build();
+
+        let limits = MultipartLimits {
+            max_part_size: Some(10_000),
+            ..MultipartLimits::default()
+        };
+
+        let mut multipart = Multipart::from_request(&mut req).unwrap().limits(limits);
+        let err = multipart.next_part().unwrap_err();
+        assert_eq!(err.status(), HttpStatus::PayloadTooLarge);
+    }
+
+    #[test]
+    fn default_limits() {
+        let builder = (0..101).fold(MultipartBuilder::new(), |b, i| {
+            b.text(&format!("field{}", i), "value")
+        });
+
+        let mut req = RequestBuilder::new().multipart(builder).build();
+        let mut multipart = Multipart::from_request(&mut req).unwrap();
+
+        for _ in 0..100 {
+            assert!(multipart.next_part().unwrap().is_some());
+        }
+
+        let err = multipart.next_part().unwrap_err();
+        assert_eq!(err.status(), HttpStatus::PayloadTooLarge);
+    }
+
+    #[test]
+    fn truncated_body() {
+        let body = "--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nno end";
+
+        let mut multipart = Multipart::new(Cursor::new(body), "XyZ");
+        let err = multipart.next_part().unwrap_err();
+        assert_eq!(err.status(), HttpStatus::BadRequest);
+
+        let body = "--XyZ\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nend\r\n--XyZ--";
+
+        let mut multipart = Multipart::new(Cursor::new(body), "XyZ");
+        assert_eq!(
+            multipart.next_part().unwrap().unwrap().text().unwrap(),
+            "end"
+        );
+        assert!(multipart.next_part().unwrap().is_none());
+    }
+
+    #[test]
+    fn parse_disposition() {
+        let value = "form-data; name=field; filename*=UTF-8''na%C3%AFve.txt";
+
+        assert_eq!(disposition_param(value, "name").as_deref(), Some("field"));
+        assert_eq!(
+            disposition_param(value, "filename*").as_deref(),
+            Some("UTF-8''na%C3%AFve.txt")
+        );
+        assert_eq!(disposition_param(value, "filename"), None);
+    }
+}
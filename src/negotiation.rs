@@ -0,0 +1,204 @@
+use std::slice::Iter;
+
+/// A value of a quality-valued header such as `Accept`, with its `q` weight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityItem {
+    pub value: String,
+    pub quality: f32,
+}
+
+/// The parsed value of an `Accept`, `Accept-Language`, `Accept-Charset` or `Accept-Encoding`
+/// header, ordered from most to least preferred.
+///
+/// Items with equal quality keep the order they were sent in. Parameters other than `q` are
+/// ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QualityList(Vec<QualityItem>);
+
+impl QualityList {
+    pub fn parse(header: &str) -> Self {
+        let mut items = header
+            .split(',')
+            .filter_map(|item| {
+                let mut params = item.split(';').map(str::trim);
+
+                let value = params.next().filter(|v| !v.is_empty())?;
+
+                let quality = params
+                    .filter_map(|p| p.split_once('='))
+                    .find(|(k, _)| k.trim().eq_ignore_ascii_case("q"))
+                    .map(|(_, q)| {
+                        q.trim()
+                            .parse::<f32>()
+                            .ok()
+                            .filter(|q| (0.0..=1.0).contains(q))
+                    })
+                    .unwrap_or(Some(1.0))?;
+
+                Some(QualityItem {
+                    value: value.to_owned(),
+                    quality,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        items.sort_by(|a, b| b.quality.total_cmp(&a.quality));
+
+        Self(items)
+    }
+
+    pub fn iter(&self) -> Iter<'_, QualityItem> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The best of the `available` media types for this `Accept` list. `text/*` and `*/*`
+    /// ranges apply to types not listed more specifically.
+    pub fn best_media_type<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        self.best_match(available, |range, media_type| {
+            let essence = |s: &str| s.split(';').next().unwrap_or_default().trim().to_owned();
+
+            let (range, media_type) = (essence(range), essence(media_type));
+
+            let (range_type, range_subtype) = range.split_once('/')?;
+            let (media_type, media_subtype) = media_type.split_once('/')?;
+
+            if range_type == "*" && range_subtype == "*" {
+                Some(0)
+            } else if !range_type.eq_ignore_ascii_case(media_type) {
+                None
+            } else if range_subtype == "*" {
+                Some(1)
+            } else {
+                range_subtype
+                    .eq_ignore_ascii_case(media_subtype)
+                    .then_some(2)
+            }
+        })
+    }
+
+    /// The best of the `available` language tags for this `Accept-Language` list. A range such as
+    /// `en` matches `en` and `en-GB`.
+    pub fn best_language<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        self.best_match(available, |range, tag| {
+            if range == "*" {
+                Some(0)
+            } else if tag.eq_ignore_ascii_case(range)
+                || (tag.len() > range.len()
+                    && tag.as_bytes()[range.len()] == b'-'
+                    && tag[..range.len()].eq_ignore_ascii_case(range))
+            {
+                Some(range.len())
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The best of the `available` charsets for this `Accept-Charset` list.
+    pub fn best_charset<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        self.best_match(available, |range, charset| match range {
+            "*" => Some(0),
+            _ => charset.eq_ignore_ascii_case(range).then_some(1),
+        })
+    }
+
+    /// The available value with the highest quality, using the order of `available` to break
+    /// ties. `specificity` returns how closely a range matches a value, or `None` if it does not,
+    /// and the most specific matching range determines the quality of a value.
+    pub fn best_match<'a, F>(&self, available: &[&'a str], specificity: F) -> Option<&'a str>
+    where
+        F: Fn(&str, &str) -> Option<usize>,
+    {
+        available
+            .iter()
+            .filter_map(|a| {
+                self.0
+                    .iter()
+                    .filter_map(|item| specificity(&item.value, a).map(|s| (s, item.quality)))
+                    .fold(None, |best: Option<(usize, f32)>, (s, q)| match best {
+                        Some((best_s, _)) if best_s >= s => best,
+                        _ => Some((s, q)),
+                    })
+                    .map(|(_, q)| (*a, q))
+            })
+            .filter(|(_, q)| *q > 0.0)
+            .fold(None, |best: Option<(&'a str, f32)>, (a, q)| match best {
+                Some((_, best_q)) if best_q >= q => best,
+                _ => Some((a, q)),
+            })
+            .map(|(a, _)| a)
+    }
+}
+
+impl<'a> IntoIterator for &'a QualityList {
+    type Item = &'a QualityItem;
+    type IntoIter = Iter<'a, QualityItem>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QualityList;
+
+    #[test]
+    fn parse_quality_list() {
+        let list =
+            QualityList::parse("text/html;level=1, application/json;q=0.9, */*;q=0.1, bad;q=2");
+
+        let values = list.iter().map(|i| i.value.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(values, ["text/html", "application/json", "*/*"]);
+        assert_eq!(list.iter().nth(1).unwrap().quality, 0.9);
+    }
+
+    #[test]
+    fn negotiate_media_types() {
+        let available = ["application/json", "text/html; charset=utf-8"];
+
+        let best = |accept: &str| QualityList::parse(accept).best_media_type(&available);
+
+        assert_eq!(
+            best("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(best("application/json"), Some("application/json"));
+        assert_eq!(
+            best("text/*;q=0.5, application/*;q=0.9"),
+            Some("application/json")
+        );
+        assert_eq!(best("*/*"), Some("application/json"));
+        assert_eq!(
+            best("*/*, application/json;q=0"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(best("image/png"), None);
+    }
+
+    #[test]
+    fn negotiate_languages_and_charsets() {
+        let list = QualityList::parse("en-GB,en;q=0.5,fr;q=0.7");
+
+        assert_eq!(list.best_language(&["fr", "en-US"]), Some("fr"));
+        assert_eq!(list.best_language(&["en-GB", "fr"]), Some("en-GB"));
+        assert_eq!(list.best_language(&["de"]), None);
+
+        let list = QualityList::parse("iso-8859-1, *;q=0.1");
+
+        assert_eq!(
+            list.best_charset(&["utf-8", "ISO-8859-1"]),
+            Some("ISO-8859-1")
+        );
+        assert_eq!(list.best_charset(&["utf-8"]), Some("utf-8"));
+    }
+}
@@ -0,0 +1,224 @@
+use std::{collections::HashMap, str::FromStr};
+
+use crate::{error::HttpError, http_status::HttpStatus};
+
+/// Decoded `application/x-www-form-urlencoded` key/value pairs, such as a query string.
+///
+/// Keys may appear more than once and their order is preserved.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Params(Vec<(String, String)>);
+
+impl Params {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse(s: &str) -> Self {
+        let pairs = s
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let (k, v) = p.split_once('=').unwrap_or((p, ""));
+
+                (percent_decode(k, true), percent_decode(v, true))
+            })
+            .collect();
+
+        Params(pairs)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_all<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.0
+            .iter()
+            .filter(move |(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_as<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key).and_then(|v| v.parse().ok())
+    }
+
+    /// Parses the first value of `key`, failing with a `400 Bad Request` if it is missing or invalid.
+    pub fn required<T: FromStr>(&self, key: &str) -> std::result::Result<T, HttpError> {
+        let value = self.get(key).ok_or_else(|| {
+            HttpError::new(
+                format!("Missing parameter '{}'.", key),
+                HttpStatus::BadRequest,
+            )
+        })?;
+
+        value.parse::<T>().map_err(|_| {
+            HttpError::new(
+                format!(
+                    "Invalid parameter type. Expected parameter '{}' to be of type {}.",
+                    key,
+                    std::any::type_name::<T>()
+                ),
+                HttpStatus::BadRequest,
+            )
+        })
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.0.iter().any(|(k, _)| k == key)
+    }
+
+    pub fn append<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) {
+        self.0.push((key.into(), value.into()));
+    }
+
+    pub fn with<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.append(key, value);
+        self
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl std::fmt::Display for Params {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (k, v)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "&")?;
+            }
+
+            write!(f, "{}={}", percent_encode(k), percent_encode(v))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Params {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Params(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+pub trait FromParams: Sized {
+    fn from_params(params: &Params) -> std::result::Result<Self, HttpError>;
+}
+
+impl FromParams for Params {
+    fn from_params(params: &Params) -> std::result::Result<Self, HttpError> {
+        Ok(params.clone())
+    }
+}
+
+impl FromParams for HashMap<String, String> {
+    fn from_params(params: &Params) -> std::result::Result<Self, HttpError> {
+        Ok(params
+            .iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect())
+    }
+}
+
+pub fn percent_decode(s: &str, plus_as_space: bool) -> String {
+    let bytes = s.as_bytes();
+
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' if plus_as_space => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3])
+                    .ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+
+                if let Some(b) = hex {
+                    decoded.push(b);
+                    i += 2;
+                } else {
+                    decoded.push(b'%');
+                }
+            }
+            b => decoded.push(b),
+        }
+
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Encodes `s` for use in a form body or query string, with spaces written as `+`.
+pub fn percent_encode(s: &str) -> String {
+    encode(s, true)
+}
+
+/// Encodes `s` for use as a single path segment, with spaces written as `%20`.
+pub fn percent_encode_path_segment(s: &str) -> String {
+    encode(s, false)
+}
+
+fn encode(s: &str, space_as_plus: bool) -> String {
+    let mut encoded = String::with_capacity(s.len());
+
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(b as char)
+            }
+            b' ' if space_as_plus => encoded.push('+'),
+            b => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{percent_decode, percent_encode, Params};
+
+    #[test]
+    fn parse_params() {
+        let params = Params::parse("name=Zak+M&age=24&tag=a&tag=b%26c&empty=&flag");
+
+        assert_eq!(params.get("name"), Some("Zak M"));
+        assert_eq!(params.get_as::<u8>("age"), Some(24));
+        assert_eq!(params.get_all("tag").collect::<Vec<_>>(), vec!["a", "b&c"]);
+        assert_eq!(params.get("empty"), Some(""));
+        assert!(params.contains_key("flag"));
+        assert!(params.required::<u8>("name").is_err());
+        assert!(params.required::<u8>("missing").is_err());
+    }
+
+    #[test]
+    fn encode_decode() {
+        assert_eq!(percent_encode("a b&c=d/é"), "a+b%26c%3Dd%2F%C3%A9");
+        assert_eq!(percent_decode("a+b%26c%3Dd%2F%C3%A9", true), "a b&c=d/é");
+        assert_eq!(percent_decode("a+b%2", false), "a+b%2");
+
+        let params: Params = vec![("q", "rust http"), ("page", "2")]
+            .into_iter()
+            .collect();
+
+        assert_eq!(params.to_string(), "q=rust+http&page=2");
+    }
+}
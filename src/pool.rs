@@ -37,7 +37,7 @@ impl ThreadPool {
 
         let condvar = Arc::new((Mutex::new(tasks), Condvar::new()));
 
-        let mut workers = Vec::with_capacity(num_cpus as usize);
+        let mut workers = Vec::with_capacity(num_cpus);
 
         for _ in 0..num_cpus {
             let condvar_c = condvar.clone();
@@ -0,0 +1,479 @@
+use std::{
+    io::{Read, Seek, SeekFrom},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    conditional::EntityTag,
+    error::{HttpError, HttpInternalError},
+    header_item::HeaderItem,
+    header_name::HeaderName,
+    header_value::HeaderValue,
+    http_date::HttpDate,
+    http_status::HttpStatus,
+    method::Method,
+    mime::Mime,
+    request::Request,
+    response::{Response, ResponseBuilder},
+};
+
+/// Requests with more ranges than this are served in full rather than as many small parts.
+const MAX_RANGES: usize = 32;
+
+/// One range of a `Range: bytes=...` header, before it is resolved against the length of the
+/// representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeSpec {
+    /// `bytes=500-999`
+    FromTo(u64, u64),
+    /// `bytes=500-`
+    From(u64),
+    /// `bytes=-500`, the last 500 bytes.
+    Suffix(u64),
+}
+
+/// An inclusive range of bytes within a representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// The value of a `Range` header using the `bytes` unit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range(Vec<RangeSpec>);
+
+impl Range {
+    pub fn specs(&self) -> &[RangeSpec] {
+        &self.0
+    }
+
+    /// Resolves the ranges against a representation of `len` bytes, dropping unsatisfiable ranges
+    /// and merging those that overlap or touch. An empty result means the range is not
+    /// satisfiable.
+    pub fn resolve(&self, len: u64) -> Vec<ByteRange> {
+        let mut ranges = self
+            .0
+            .iter()
+            .filter_map(|spec| {
+                let (start, end) = match *spec {
+                    RangeSpec::FromTo(start, end) => (start, end.min(len.checked_sub(1)?)),
+                    RangeSpec::From(start) => (start, len.checked_sub(1)?),
+                    RangeSpec::Suffix(0) => return None,
+                    RangeSpec::Suffix(n) => (len.saturating_sub(n), len.checked_sub(1)?),
+                };
+
+                (start < len).then_some(ByteRange { start, end })
+            })
+            .collect::<Vec<_>>();
+
+        ranges.sort_by_key(|r| r.start);
+
+        ranges.into_iter().fold(Vec::new(), |mut merged, r| {
+            match merged.last_mut() {
+                Some(last) if r.start <= last.end.saturating_add(1) => {
+                    last.end = last.end.max(r.end);
+                }
+                _ => merged.push(r),
+            }
+
+            merged
+        })
+    }
+}
+
+impl FromStr for Range {
+    type Err = HttpInternalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || HttpInternalError::new(format!("Invalid range '{}'.", s));
+
+        let (unit, ranges) = s.trim().split_once('=').ok_or_else(invalid)?;
+
+        if !unit.trim().eq_ignore_ascii_case("bytes") {
+            return Err(invalid());
+        }
+
+        let parse = |n: &str| -> Option<u64> {
+            if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) {
+                n.parse().ok()
+            } else {
+                None
+            }
+        };
+
+        let specs = ranges
+            .split(',')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(|r| {
+                let (start, end) = r.split_once('-')?;
+
+                match (start.trim(), end.trim()) {
+                    ("", end) => parse(end).map(RangeSpec::Suffix),
+                    (start, "") => parse(start).map(RangeSpec::From),
+                    (start, end) => {
+                        let (start, end) = (parse(start)?, parse(end)?);
+
+                        (start <= end).then_some(RangeSpec::FromTo(start, end))
+                    }
+                }
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+
+        if specs.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self(specs))
+    }
+}
+
+/// A `Content-Range` header value, e.g. `bytes 0-499/1234` or `bytes */1234`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentRange {
+    pub range: Option<ByteRange>,
+    pub complete_len: u64,
+}
+
+impl std::fmt::Display for ContentRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.range {
+            Some(r) => write!(f, "bytes {}-{}/{}", r.start, r.end, self.complete_len),
+            None => write!(f, "bytes */{}", self.complete_len),
+        }
+    }
+}
+
+impl From<ContentRange> for HeaderValue {
+    fn from(content_range: ContentRange) -> Self {
+        Self::from_trusted(content_range.to_string())
+    }
+}
+
+/// The value of an `If-Range` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfRange {
+    EntityTag(EntityTag),
+    Date(HttpDate),
+}
+
+impl IfRange {
+    /// Whether the range should be served, which requires the validator to be unchanged. Only
+    /// strong entity tags match.
+    pub fn matches(&self, etag: Option<&EntityTag>, last_modified: Option<HttpDate>) -> bool {
+        match self {
+            IfRange::EntityTag(tag) => etag.is_some_and(|e| tag.strong_eq(e)),
+            IfRange::Date(date) => last_modified.is_some_and(|l| l == *date),
+        }
+    }
+}
+
+impl FromStr for IfRange {
+    type Err = HttpInternalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if s.starts_with('"') || s.starts_with("W/") {
+            s.parse().map(IfRange::EntityTag)
+        } else {
+            s.parse().map(IfRange::Date)
+        }
+    }
+}
+
+/// A seekable body served with support for `Range` and `If-Range` requests.
+///
+/// A range request reads only the requested ranges, so resuming a download does not load the
+/// rest of the file. Every other response holds the whole body in memory: requests without a
+/// range, `HEAD` requests, ranges ignored because of `If-Range`, and requests asking for too many
+/// ranges.
+#[derive(Debug)]
+pub struct RangedBody<R> {
+    reader: R,
+    len: u64,
+    content_type: Mime,
+    etag: Option<EntityTag>,
+    last_modified: Option<HttpDate>,
+}
+
+impl<R: Read + Seek> RangedBody<R> {
+    pub fn new(mut reader: R, content_type: Mime) -> std::io::Result<Self> {
+        let len = reader.seek(SeekFrom::End(0))?;
+
+        Ok(Self {
+            reader,
+            len,
+            content_type,
+            etag: None,
+            last_modified: None,
+        })
+    }
+
+    pub fn etag(mut self, etag: EntityTag) -> Self {
+        self.etag = Some(etag);
+        self
+    }
+
+    pub fn last_modified(mut self, last_modified: HttpDate) -> Self {
+        self.last_modified = Some(last_modified);
+        self
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Responds with `206 Partial Content` for a satisfiable `Range` request,
+    /// `416 Range Not Satisfiable` for an unsatisfiable one and the full body otherwise.
+    pub fn respond(mut self, req: &Request) -> std::result::Result<Response, HttpError> {
+        let header_map = req.header.header_map();
+
+        let mut builder = ResponseBuilder::new()
+            .header(HeaderName::ACCEPT_RANGES, HeaderValue::from_static("bytes"))
+            .content_type(self.content_type.clone());
+
+        if let Some(etag) = &self.etag {
+            builder = builder.etag(etag);
+        }
+
+        if let Some(last_modified) = self.last_modified {
+            builder = builder.last_modified(last_modified);
+        }
+
+        let if_range_matches = header_map
+            .get_by_str_key("if-range")
+            .map(|v| {
+                v.parse::<IfRange>()
+                    .is_ok_and(|i| i.matches(self.etag.as_ref(), self.last_modified))
+            })
+            .unwrap_or(true);
+
+        let range = header_map.get_by_str_key_as::<Range>("range").filter(|r| {
+            req.header.method == Method::GET && if_range_matches && r.specs().len() <= MAX_RANGES
+        });
+
+        let range = match range {
+            Some(range) => range,
+            None => {
+                let contents = self.read_range(ByteRange {
+                    start: 0,
+                    end: self.len.saturating_sub(1),
+                })?;
+
+                return Ok(builder.body(contents).build());
+            }
+        };
+
+        let ranges = range.resolve(self.len);
+
+        match ranges.as_slice() {
+            [] => Ok(builder
+                .status(HttpStatus::RequestedRangeNotSatisfiable)
+                .header(
+                    HeaderName::CONTENT_RANGE,
+                    ContentRange {
+                        range: None,
+                        complete_len: self.len,
+                    }
+                    .into(),
+                )
+                .build()),
+            [range] => {
+                let contents = self.read_range(*range)?;
+
+                Ok(builder
+                    .status(HttpStatus::PartialContent)
+                    .header(
+                        HeaderName::CONTENT_RANGE,
+                        ContentRange {
+                            range: Some(*range),
+                            complete_len: self.len,
+                        }
+                        .into(),
+                    )
+                    .body(contents)
+                    .build())
+            }
+            ranges => {
+                let boundary = byteranges_boundary();
+
+                let mut body = Vec::new();
+
+                for range in ranges {
+                    body.extend_from_slice(
+                        format!(
+                            "--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+                            boundary,
+                            self.content_type,
+                            ContentRange {
+                                range: Some(*range),
+                                complete_len: self.len,
+                            }
+                        )
+                        .as_bytes(),
+                    );
+                    body.extend_from_slice(&self.read_range(*range)?);
+                    body.extend_from_slice(b"\r\n");
+                }
+
+                body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+
+                Ok(builder
+                    .status(HttpStatus::PartialContent)
+                    .header(
+                        HeaderName::CONTENT_TYPE,
+                        HeaderValue::from_trusted(format!(
+                            "{}; boundary={}",
+                            Mime::MULTIPART_BYTERANGES,
+                            boundary
+                        )),
+                    )
+                    .body(body)
+                    .build())
+            }
+        }
+    }
+
+    fn read_range(&mut self, range: ByteRange) -> std::result::Result<Vec<u8>, HttpError> {
+        if self.len == 0 {
+            return Ok(Vec::new());
+        }
+
+        let read_error = |_| HttpError::internal_server_error("Failed to read body.");
+
+        self.reader
+            .seek(SeekFrom::Start(range.start))
+            .map_err(read_error)?;
+
+        let mut contents = Vec::new();
+
+        self.reader
+            .by_ref()
+            .take(range.end - range.start + 1)
+            .read_to_end(&mut contents)
+            .map_err(read_error)?;
+
+        Ok(contents)
+    }
+}
+
+fn byteranges_boundary() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+
+    format!("http_lib2-byteranges-{:x}", nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        conditional::EntityTag, header_item::HeaderItem, mime::Mime, request::RequestBuilder,
+        response::Response,
+    };
+
+    use super::{ByteRange, ContentRange, Range, RangeSpec, RangedBody};
+
+    fn ranged(headers: &[(&str, &str)]) -> Response {
+        let mut req = RequestBuilder::new();
+
+        for (k, v) in headers {
+            req = req.insert_header_key_val(k, v).unwrap();
+        }
+
+        RangedBody::new(Cursor::new(b"0123456789".to_vec()), Mime::TEXT_PLAIN)
+            .unwrap()
+            .etag(EntityTag::strong("v1"))
+            .respond(&req.build())
+            .unwrap()
+    }
+
+    fn header<'a>(res: &'a Response, key: &str) -> Option<&'a str> {
+        res.header.header_map().get_by_str_key(key)
+    }
+
+    #[test]
+    fn parse_and_resolve() {
+        let range: Range = "bytes=0-1, 5-, -2".parse().unwrap();
+
+        assert_eq!(
+            range.specs(),
+            [
+                RangeSpec::FromTo(0, 1),
+                RangeSpec::From(5),
+                RangeSpec::Suffix(2)
+            ]
+        );
+        assert_eq!(
+            range.resolve(10),
+            [
+                ByteRange { start: 0, end: 1 },
+                ByteRange { start: 5, end: 9 }
+            ]
+        );
+
+        assert_eq!(
+            "bytes=-20".parse::<Range>().unwrap().resolve(10),
+            [ByteRange { start: 0, end: 9 }]
+        );
+        assert!("bytes=20-".parse::<Range>().unwrap().resolve(10).is_empty());
+        assert!("bytes=5-1".parse::<Range>().is_err());
+        assert!("items=0-1".parse::<Range>().is_err());
+
+        assert_eq!(
+            ContentRange {
+                range: Some(ByteRange { start: 0, end: 1 }),
+                complete_len: 10
+            }
+            .to_string(),
+            "bytes 0-1/10"
+        );
+    }
+
+    #[test]
+    fn ranged_responses() {
+        let res = ranged(&[]);
+        assert_eq!(res.header.status_code, 200);
+        assert_eq!(header(&res, "accept-ranges"), Some("bytes"));
+        assert_eq!(res.body.contents, b"0123456789");
+
+        let res = ranged(&[("Range", "bytes=2-4")]);
+        assert_eq!(res.header.status_code, 206);
+        assert_eq!(header(&res, "content-range"), Some("bytes 2-4/10"));
+        assert_eq!(res.body.contents, b"234");
+
+        let res = ranged(&[("Range", "bytes=20-")]);
+        assert_eq!(res.header.status_code, 416);
+        assert_eq!(header(&res, "content-range"), Some("bytes */10"));
+
+        let res = ranged(&[("Range", "bytes=0-0,-1")]);
+        assert_eq!(res.header.status_code, 206);
+
+        let content_type = header(&res, "content-type").unwrap();
+        let boundary = content_type.split_once("boundary=").unwrap().1;
+        let body = String::from_utf8(res.body.contents.clone()).unwrap();
+
+        assert!(content_type.starts_with("multipart/byteranges"));
+        assert!(body.contains("Content-Range: bytes 0-0/10\r\n\r\n0\r\n"));
+        assert!(body.contains("Content-Range: bytes 9-9/10\r\n\r\n9\r\n"));
+        assert!(body.ends_with(&format!("--{}--\r\n", boundary)));
+
+        let res = ranged(&[("Range", "bytes=2-4"), ("If-Range", "\"v1\"")]);
+        assert_eq!(res.header.status_code, 206);
+
+        let res = ranged(&[("Range", "bytes=2-4"), ("If-Range", "\"v0\"")]);
+        assert_eq!(res.header.status_code, 200);
+        assert_eq!(res.body.contents, b"0123456789");
+    }
+}
@@ -0,0 +1,368 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::HttpError,
+    header_item::HeaderItem,
+    header_map::HeaderMap,
+    header_name::HeaderName,
+    middleware::{Middleware, Next},
+    request::ServerRequest,
+    response::{HttpResponse, Response},
+};
+
+type KeyFn = Box<dyn Fn(&ServerRequest) -> Option<String> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// The outcome of taking a token from a bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Decision {
+    allowed: bool,
+    remaining: u32,
+    /// Seconds until the bucket is full again.
+    reset: u64,
+    /// Seconds until the next request would be allowed.
+    retry_after: u64,
+}
+
+#[derive(Debug)]
+struct Buckets {
+    buckets: HashMap<String, Bucket>,
+    swept: Instant,
+}
+
+/// Middleware limiting how often each client can make requests, answering with
+/// `429 Too Many Requests` and a `Retry-After` header once the limit is reached.
+///
+/// Each client has a token bucket holding up to `limit` requests, which refills at `limit`
+/// requests per `period`, so short bursts are allowed while the average rate is capped. Every
+/// response gets `RateLimit-Limit`, `RateLimit-Remaining` and `RateLimit-Reset` headers.
+///
+/// Clients are told apart by their IP address by default. Behind a proxy, use `key_by` to read
+/// the address the proxy forwards, since every request would otherwise share the proxy's limit.
+///
+/// At most `max_buckets` clients are tracked at once. Once that many have used some of their
+/// limit, requests from new clients are rejected until older buckets have refilled.
+pub struct RateLimit {
+    limit: u32,
+    period: Duration,
+    max_buckets: usize,
+    key: Option<KeyFn>,
+    buckets: Mutex<Buckets>,
+}
+
+impl std::fmt::Debug for RateLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimit")
+            .field("limit", &self.limit)
+            .field("period", &self.period)
+            .field("max_buckets", &self.max_buckets)
+            .finish()
+    }
+}
+
+impl RateLimit {
+    /// Allows `limit` requests per `period` for each client.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is zero or `period` is zero.
+    pub fn new(limit: u32, period: Duration) -> Self {
+        assert!(limit > 0, "Rate limit must allow at least one request.");
+        assert!(!period.is_zero(), "Rate limit period must not be zero.");
+
+        Self {
+            limit,
+            period,
+            max_buckets: 10_000,
+            key: None,
+            buckets: Mutex::new(Buckets {
+                buckets: HashMap::new(),
+                swept: Instant::now(),
+            }),
+        }
+    }
+
+    /// The most clients tracked at once. Defaults to 10,000.
+    pub fn max_buckets(mut self, max_buckets: usize) -> Self {
+        self.max_buckets = max_buckets;
+        self
+    }
+
+    /// Limits clients by the value of `name`, such as an API key header. Requests without the
+    /// header are limited by IP address.
+    ///
+    /// The value is used as sent, so a client can get a new limit just by changing it. Only use
+    /// a header that is checked before this middleware runs, such as an API key rejected by
+    /// earlier middleware when it is unknown.
+    pub fn key_by_header(self, name: HeaderName) -> Self {
+        self.key_by(move |req| {
+            req.request
+                .header
+                .header_map()
+                .get_by_str_key(name.as_str())
+                .map(str::to_owned)
+        })
+    }
+
+    /// Limits clients by the key `key` returns for a request. Requests it returns `None` for
+    /// are limited by IP address.
+    pub fn key_by<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&ServerRequest) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = Some(Box::new(key));
+        self
+    }
+
+    fn buckets(&self) -> MutexGuard<'_, Buckets> {
+        self.buckets.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn key(&self, req: &ServerRequest) -> String {
+        // Keys are prefixed so a custom key cannot use up the limit of an IP address.
+        match self.key.as_ref().and_then(|key| key(req)) {
+            Some(key) => format!("key:{}", key),
+            None => format!("ip:{}", req.peer_address.ip()),
+        }
+    }
+
+    /// Tokens added to a bucket per second.
+    fn rate(&self) -> f64 {
+        self.limit as f64 / self.period.as_secs_f64()
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+
+        bucket.tokens = (bucket.tokens + elapsed * self.rate()).min(self.limit as f64);
+        bucket.updated = now;
+    }
+
+    fn check(&self, key: String, now: Instant) -> Decision {
+        let mut buckets = self.buckets();
+
+        let is_full = |buckets: &Buckets| {
+            buckets.buckets.len() >= self.max_buckets && !buckets.buckets.contains_key(&key)
+        };
+
+        // Buckets that have refilled completely are the same as new ones, so they are dropped to
+        // keep memory bounded by the number of recently active clients.
+        if now.saturating_duration_since(buckets.swept) >= self.period || is_full(&buckets) {
+            buckets.buckets.retain(|_, bucket| {
+                self.refill(bucket, now);
+                bucket.tokens < self.limit as f64
+            });
+            buckets.swept = now;
+        }
+
+        // With no room for another bucket, a new client is treated as having an empty one.
+        let mut overflow = Bucket {
+            tokens: 0.0,
+            updated: now,
+        };
+
+        let bucket = if is_full(&buckets) {
+            &mut overflow
+        } else {
+            buckets.buckets.entry(key).or_insert(Bucket {
+                tokens: self.limit as f64,
+                updated: now,
+            })
+        };
+
+        self.refill(bucket, now);
+
+        let allowed = bucket.tokens >= 1.0;
+
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        let seconds_until = |tokens: f64| ((tokens - bucket.tokens).max(0.0) / self.rate()).ceil();
+
+        Decision {
+            allowed,
+            remaining: bucket.tokens as u32,
+            reset: seconds_until(self.limit as f64) as u64,
+            retry_after: seconds_until(1.0) as u64,
+        }
+    }
+
+    fn add_headers(&self, header_map: &mut HeaderMap, decision: &Decision) {
+        header_map.insert(HeaderName::RATELIMIT_LIMIT, self.limit.into());
+        header_map.insert(HeaderName::RATELIMIT_REMAINING, decision.remaining.into());
+        header_map.insert(HeaderName::RATELIMIT_RESET, decision.reset.into());
+    }
+}
+
+impl Middleware for RateLimit {
+    fn handle(&self, req: ServerRequest, next: Next<'_>) -> Response {
+        let decision = self.check(self.key(&req), Instant::now());
+
+        let mut res = if decision.allowed {
+            next.run(req)
+        } else {
+            let error = HttpError::too_many_requests("Too many requests.")
+                .header(HeaderName::RETRY_AFTER, decision.retry_after.into());
+
+            Box::new(error).into_response()
+        };
+
+        self.add_headers(res.header.header_map_mut(), &decision);
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        header_item::HeaderItem,
+        header_name::HeaderName,
+        middleware::{Middleware, Next},
+        request::{RequestBuilder, ServerRequest},
+        response::{Response, ResponseBuilder},
+        route::RouteKey,
+    };
+
+    use super::{Decision, RateLimit};
+
+    fn send(limit: &RateLimit, ip: [u8; 4], api_key: Option<&str>) -> Response {
+        let mut req = RequestBuilder::new();
+
+        if let Some(api_key) = api_key {
+            req = req.insert_header_key_val("X-Api-Key", api_key).unwrap();
+        }
+
+        let req = ServerRequest::new(
+            RouteKey("/".to_owned()),
+            req.build(),
+            SocketAddr::from((ip, 4000)),
+        );
+
+        let endpoint = |_: ServerRequest| -> Response { ResponseBuilder::new().body("ok").build() };
+
+        let middleware: Vec<Box<dyn Middleware>> = Vec::new();
+
+        limit.handle(req, Next::new(&middleware, &endpoint))
+    }
+
+    fn header<'a>(res: &'a Response, name: &str) -> Option<&'a str> {
+        res.header.header_map().get_by_str_key(name)
+    }
+
+    #[test]
+    fn limits_each_client() {
+        let limit = RateLimit::new(2, Duration::from_secs(60));
+
+        let res = send(&limit, [10, 0, 0, 1], None);
+        assert_eq!(res.header.status_code, 200);
+        assert_eq!(header(&res, "ratelimit-limit"), Some("2"));
+        assert_eq!(header(&res, "ratelimit-remaining"), Some("1"));
+        assert_eq!(header(&res, "ratelimit-reset"), Some("30"));
+        assert_eq!(header(&res, "retry-after"), None);
+
+        send(&limit, [10, 0, 0, 1], None);
+
+        let res = send(&limit, [10, 0, 0, 1], None);
+        assert_eq!(res.header.status_code, 429);
+        assert_eq!(header(&res, "retry-after"), Some("30"));
+        assert_eq!(header(&res, "ratelimit-remaining"), Some("0"));
+
+        assert_eq!(send(&limit, [10, 0, 0, 2], None).header.status_code, 200);
+
+        let limit = RateLimit::new(1, Duration::from_secs(60))
+            .key_by_header(HeaderName::from_static("X-Api-Key"));
+
+        assert_eq!(
+            send(&limit, [10, 0, 0, 1], Some("a")).header.status_code,
+            200
+        );
+        assert_eq!(
+            send(&limit, [10, 0, 0, 2], Some("a")).header.status_code,
+            429
+        );
+        assert_eq!(
+            send(&limit, [10, 0, 0, 1], Some("b")).header.status_code,
+            200
+        );
+        assert_eq!(send(&limit, [10, 0, 0, 1], None).header.status_code, 200);
+        assert_eq!(send(&limit, [10, 0, 0, 1], None).header.status_code, 429);
+    }
+
+    #[test]
+    fn buckets_refill() {
+        let limit = RateLimit::new(10, Duration::from_secs(10));
+
+        let start = Instant::now();
+        let check = |secs: f64| limit.check("a".to_owned(), start + Duration::from_secs_f64(secs));
+
+        for _ in 0..10 {
+            assert!(check(0.0).allowed);
+        }
+
+        assert_eq!(
+            check(0.5),
+            Decision {
+                allowed: false,
+                remaining: 0,
+                reset: 10,
+                retry_after: 1,
+            }
+        );
+
+        assert!(check(1.0).allowed);
+        assert!(!check(1.0).allowed);
+
+        let decision = check(4.5);
+        assert!(decision.allowed);
+        assert_eq!((decision.remaining, decision.reset), (2, 8));
+
+        assert_eq!(limit.buckets().buckets.len(), 1);
+
+        // A full bucket is dropped once a period has passed, and a request after that starts
+        // with a new full bucket.
+        assert_eq!(check(60.0).remaining, 9);
+        assert_eq!(limit.buckets().buckets.len(), 1);
+
+        limit.check("b".to_owned(), start + Duration::from_secs(120));
+        assert_eq!(limit.buckets().buckets.len(), 1);
+    }
+
+    #[test]
+    fn max_buckets() {
+        let limit = RateLimit::new(10, Duration::from_secs(10)).max_buckets(2);
+
+        let start = Instant::now();
+        let check = |key: &str, secs: f64| {
+            limit.check(key.to_owned(), start + Duration::from_secs_f64(secs))
+        };
+
+        assert!(check("a", 0.0).allowed);
+        assert!(check("b", 0.0).allowed);
+
+        let decision = check("c", 0.0);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after, 1);
+        assert!(check("a", 0.0).allowed);
+        assert_eq!(limit.buckets().buckets.len(), 2);
+
+        // Once a bucket has refilled it is dropped, making room for a new client.
+        assert!(check("c", 1.5).allowed);
+        assert_eq!(limit.buckets().buckets.len(), 2);
+    }
+}
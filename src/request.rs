@@ -1,15 +1,25 @@
 use std::io::Write;
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::sync::Arc;
 
 use crate::body::Body;
+use crate::conditional::{EntityTag, Preconditions};
 use crate::error::{HttpError, HttpInternalError};
+use crate::extensions::Extensions;
 use crate::header_item::HeaderItem;
 use crate::header_map::HeaderMap;
+use crate::header_name::HeaderName;
+use crate::header_value::HeaderValue;
+use crate::http_date::HttpDate;
 use crate::http_item::HttpItem;
 use crate::http_status::HttpStatus;
 use crate::method::Method;
-use crate::route::RouteKey;
+use crate::mime::Mime;
+use crate::negotiation::QualityList;
+use crate::params::{percent_decode, FromParams, Params};
+use crate::route::{catch_all_path_filter, named_path_filter, RouteKey};
+use crate::typed_header::{ContentType, TypedHeader};
 use crate::Result;
 
 #[derive(Debug, Default)]
@@ -43,17 +53,46 @@ impl RequestBuilder {
         self
     }
 
-    pub fn insert_header_key_val(mut self, key: &str, val: &str) -> Self {
-        self.header.header_map.insert_by_str_key_value(key, val);
+    /// Sets the header `name` to `value`, replacing any existing values.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.header.header_map.insert(name, value);
         self
     }
 
+    /// Adds `value` to the header `name`, keeping any existing values.
+    pub fn append_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.header.header_map.append(name, value);
+        self
+    }
+
+    /// Sets the typed header `header`, replacing any existing values.
+    pub fn typed_header<H: TypedHeader>(mut self, header: H) -> Self {
+        self.header.header_map.typed_insert(header);
+        self
+    }
+
+    /// Like `header`, failing if `key` or `val` is not a valid header name or value.
+    pub fn insert_header_key_val(mut self, key: &str, val: &str) -> Result<Self> {
+        self.header.header_map.insert_by_str_key_value(key, val)?;
+        Ok(self)
+    }
+
+    pub fn content_type(self, mime: Mime) -> Self {
+        self.header(HeaderName::CONTENT_TYPE, mime.into())
+    }
+
+    /// Sets `params` as an `application/x-www-form-urlencoded` body.
+    pub fn form(self, params: &Params) -> Self {
+        self.content_type(Mime::APPLICATION_WWW_FORM_URLENCODED)
+            .body(params.to_string())
+    }
+
     pub fn body<T: AsRef<[u8]>>(mut self, body: T) -> Self {
         let body_len = body.as_ref().len();
 
         self.header
             .header_map
-            .insert_by_str_key_value("Content-Length", &body_len.to_string());
+            .insert(HeaderName::CONTENT_LENGTH, body_len.into());
 
         self.body = Some(Body::new(body));
         self
@@ -72,6 +111,8 @@ pub struct ServerRequest {
     route_key: RouteKey,
     pub request: Request,
     pub peer_address: SocketAddr,
+    state: Arc<Extensions>,
+    extensions: Extensions,
 }
 
 impl ServerRequest {
@@ -80,20 +121,38 @@ impl ServerRequest {
             route_key,
             request,
             peer_address,
+            state: Arc::default(),
+            extensions: Extensions::new(),
         }
     }
 
+    pub(crate) fn with_state(mut self, state: Arc<Extensions>) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Application state registered with `Server::with_state`.
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.state.get::<T>()
+    }
+
+    /// Values attached to this request only, usually by middleware.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
     pub fn path<T>(&self, path_name: &'static str) -> std::result::Result<T, HttpError>
     where
         T: FromStr,
     {
-        let path_name_delimited = format!("{{{}}}", path_name);
-
-        self.route_key
-            .0
-            .split('/')
-            .position(|r| r == path_name_delimited)
-            .and_then(|p| self.request.header.uri.split('/').nth(p))
+        self.path_params()
+            .into_iter()
+            .find(|(name, _)| *name == path_name)
+            .map(|(_, res)| res)
             .and_then(|res| if res.is_empty() { None } else { Some(res) })
             .ok_or_else(|| {
                 HttpError::new(
@@ -117,6 +176,104 @@ impl ServerRequest {
                 })
             })
     }
+
+    /// All named path parameters of the matched route, percent-decoded and in the order they
+    /// appear in the route. A `{*name}` parameter holds the rest of the path.
+    pub fn path_params(&self) -> Vec<(&str, String)> {
+        let path = self.request.header.path();
+
+        let mut params = Vec::new();
+
+        for (i, (k, v)) in self.route_key.0.split('/').zip(path.split('/')).enumerate() {
+            if catch_all_path_filter(k) {
+                let rest = match i {
+                    0 => path,
+                    _ => path
+                        .match_indices('/')
+                        .nth(i - 1)
+                        .map(|(p, _)| &path[p + 1..])
+                        .unwrap_or_default(),
+                };
+
+                params.push((&k[2..k.len() - 1], percent_decode(rest, false)));
+            } else if named_path_filter(k) {
+                params.push((&k[1..k.len() - 1], percent_decode(v, false)));
+            }
+        }
+
+        if let Some(k) = self.route_key.0.split('/').nth(path.split('/').count()) {
+            if catch_all_path_filter(k) {
+                params.push((&k[2..k.len() - 1], String::new()));
+            }
+        }
+
+        params
+    }
+
+    /// Evaluates the request's `If-Match`, `If-None-Match`, `If-Modified-Since` and
+    /// `If-Unmodified-Since` headers against the current state of the resource, failing with
+    /// `412 Precondition Failed` or, for `GET` and `HEAD`, `304 Not Modified`.
+    pub fn check_preconditions(
+        &self,
+        etag: Option<&EntityTag>,
+        last_modified: Option<HttpDate>,
+    ) -> std::result::Result<(), HttpError> {
+        Preconditions::from_headers(self.request.header.header_map()).check(
+            &self.request.header.method,
+            etag,
+            last_modified,
+        )
+    }
+
+    /// Picks the media type from `available` that best matches the `Accept` header, failing with
+    /// `406 Not Acceptable` if none is acceptable. Without an `Accept` header the first available
+    /// type is chosen.
+    pub fn negotiate<'a>(&self, available: &[&'a str]) -> std::result::Result<&'a str, HttpError> {
+        self.negotiate_by("accept", available, QualityList::best_media_type)
+    }
+
+    /// Picks the language tag from `available` that best matches the `Accept-Language` header.
+    pub fn negotiate_language<'a>(
+        &self,
+        available: &[&'a str],
+    ) -> std::result::Result<&'a str, HttpError> {
+        self.negotiate_by("accept-language", available, QualityList::best_language)
+    }
+
+    /// Picks the charset from `available` that best matches the `Accept-Charset` header.
+    pub fn negotiate_charset<'a>(
+        &self,
+        available: &[&'a str],
+    ) -> std::result::Result<&'a str, HttpError> {
+        self.negotiate_by("accept-charset", available, QualityList::best_charset)
+    }
+
+    fn negotiate_by<'a>(
+        &self,
+        header: &str,
+        available: &[&'a str],
+        best: fn(&QualityList, &[&'a str]) -> Option<&'a str>,
+    ) -> std::result::Result<&'a str, HttpError> {
+        let best = match self.request.header.header_map().get_by_str_key(header) {
+            Some(accept) => best(&QualityList::parse(accept), available),
+            None => available.first().copied(),
+        };
+
+        best.ok_or_else(|| {
+            HttpError::not_acceptable(format!(
+                "None of the available representations are acceptable: {}.",
+                available.join(", ")
+            ))
+        })
+    }
+
+    pub fn query(&self) -> Params {
+        self.request
+            .header
+            .query()
+            .map(Params::parse)
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Debug)]
@@ -151,6 +308,33 @@ impl HttpItem for Request {
     }
 }
 
+impl Request {
+    /// The parsed `Content-Type` header, if present and valid.
+    pub fn content_type(&self) -> Option<Mime> {
+        self.header
+            .header_map
+            .typed_get::<ContentType>()
+            .map(|c| c.0)
+    }
+
+    /// Parses an `application/x-www-form-urlencoded` body, failing with
+    /// `415 Unsupported Media Type` if the request has a different content type.
+    pub fn form<T: FromParams>(&self) -> std::result::Result<T, HttpError> {
+        if !self
+            .content_type()
+            .is_some_and(|c| c.matches(&Mime::APPLICATION_WWW_FORM_URLENCODED))
+        {
+            return Err(HttpError::unsupported_media_type(
+                "Expected request with 'Content-Type: application/x-www-form-urlencoded'.",
+            ));
+        }
+
+        let body = String::from_utf8_lossy(&self.body.contents);
+
+        T::from_params(&Params::parse(&body))
+    }
+}
+
 #[derive(Debug)]
 pub struct RequestHeader {
     pub method: Method,
@@ -170,6 +354,24 @@ impl std::default::Default for RequestHeader {
     }
 }
 
+impl RequestHeader {
+    pub fn header_map_mut(&mut self) -> &mut HeaderMap {
+        &mut self.header_map
+    }
+
+    /// The request target without its query string.
+    pub fn path(&self) -> &str {
+        self.uri
+            .split_once('?')
+            .map(|(p, _)| p)
+            .unwrap_or(&self.uri)
+    }
+
+    pub fn query(&self) -> Option<&str> {
+        self.uri.split_once('?').map(|(_, q)| q)
+    }
+}
+
 impl FromStr for RequestHeader {
     type Err = HttpInternalError;
 
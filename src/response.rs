@@ -1,12 +1,18 @@
-use std::io::Write;
+use std::io::{Read, Write};
 use std::str::FromStr;
 
-use crate::body::Body;
+use crate::body::{write_chunked, Body};
+use crate::conditional::EntityTag;
 use crate::error::{HttpError, HttpInternalError};
 use crate::header_item::HeaderItem;
 use crate::header_map::HeaderMap;
+use crate::header_name::HeaderName;
+use crate::header_value::HeaderValue;
+use crate::http_date::HttpDate;
 use crate::http_item::HttpItem;
 use crate::http_status::HttpStatus;
+use crate::mime::Mime;
+use crate::typed_header::TypedHeader;
 use crate::Result;
 
 pub trait HttpResponse {
@@ -21,13 +27,19 @@ impl HttpResponse for Response {
 
 impl HttpResponse for &'static str {
     fn into_response(self: Box<Self>) -> Response {
-        ResponseBuilder::new().body(*self).build()
+        ResponseBuilder::text(*self).build()
+    }
+}
+
+impl HttpResponse for String {
+    fn into_response(self: Box<Self>) -> Response {
+        ResponseBuilder::text(*self).build()
     }
 }
 
 impl HttpResponse for Vec<u8> {
     fn into_response(self: Box<Self>) -> Response {
-        ResponseBuilder::new().body(*self).build()
+        ResponseBuilder::octet_stream(*self).build()
     }
 }
 
@@ -37,18 +49,93 @@ impl HttpResponse for () {
     }
 }
 
-impl<T: AsRef<[u8]>> HttpResponse for std::result::Result<T, HttpError> {
+impl HttpResponse for HttpError {
+    fn into_response(self: Box<Self>) -> Response {
+        let mut builder = ResponseBuilder::new().status(self.status());
+
+        for (k, v) in self.headers().iter() {
+            builder = builder.append_header(k.clone(), v.clone());
+        }
+
+        if matches!(
+            self.status(),
+            HttpStatus::NoContent | HttpStatus::NotModified
+        ) {
+            builder.build()
+        } else if let Some(body) = self.body() {
+            builder
+                .content_type(body.content_type.clone())
+                .body(&body.contents)
+                .build()
+        } else {
+            builder
+                .content_type(Mime::TEXT_PLAIN_UTF_8)
+                .body(self.message())
+                .build()
+        }
+    }
+}
+
+impl<R, E> HttpResponse for std::result::Result<R, E>
+where
+    R: HttpResponse,
+    E: Into<HttpError>,
+{
     fn into_response(self: Box<Self>) -> Response {
         match *self {
-            Ok(s) => ResponseBuilder::new().body(s).build(),
-            Err(e) => ResponseBuilder::new()
-                .status(HttpStatus::BadRequest)
-                .body(e.to_string())
-                .build(),
+            Ok(r) => Box::new(r).into_response(),
+            Err(e) => Box::new(e.into()).into_response(),
         }
     }
 }
 
+/// `None` responds with an empty `404 Not Found`.
+impl<T: HttpResponse> HttpResponse for Option<T> {
+    fn into_response(self: Box<Self>) -> Response {
+        match *self {
+            Some(r) => Box::new(r).into_response(),
+            None => ResponseBuilder::new().status(HttpStatus::NotFound).build(),
+        }
+    }
+}
+
+impl<T: HttpResponse> HttpResponse for (HttpStatus, T) {
+    fn into_response(self: Box<Self>) -> Response {
+        let (status, r) = *self;
+
+        let mut res = Box::new(r).into_response();
+        res.header.set_status(status);
+
+        res
+    }
+}
+
+/// Headers in the `HeaderMap` replace any with the same name set by `T`.
+impl<T: HttpResponse> HttpResponse for (HttpStatus, HeaderMap, T) {
+    fn into_response(self: Box<Self>) -> Response {
+        let (status, header_map, r) = *self;
+
+        let mut res = Box::new(r).into_response();
+        res.header.set_status(status);
+
+        for (k, _) in header_map.iter() {
+            res.header.header_map.remove(k.as_str());
+        }
+
+        for (k, v) in header_map {
+            res.header.header_map.append(k, v);
+        }
+
+        res
+    }
+}
+
+impl HttpResponse for Box<dyn HttpResponse> {
+    fn into_response(self: Box<Self>) -> Response {
+        (*self).into_response()
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct ResponseBuilder {
     header: ResponseHeader,
@@ -60,14 +147,41 @@ impl ResponseBuilder {
         Self::default()
     }
 
+    /// A response with a `text/plain; charset=utf-8` body.
+    pub fn text<T: AsRef<str>>(body: T) -> Self {
+        Self::new()
+            .content_type(Mime::TEXT_PLAIN_UTF_8)
+            .body(body.as_ref())
+    }
+
+    /// A response with a `text/html; charset=utf-8` body.
+    pub fn html<T: AsRef<str>>(body: T) -> Self {
+        Self::new()
+            .content_type(Mime::TEXT_HTML_UTF_8)
+            .body(body.as_ref())
+    }
+
+    /// A response with an already serialized `application/json` body.
+    pub fn json_str<T: AsRef<str>>(body: T) -> Self {
+        Self::new()
+            .content_type(Mime::APPLICATION_JSON)
+            .body(body.as_ref())
+    }
+
+    /// A response with an `application/octet-stream` body.
+    pub fn octet_stream<T: AsRef<[u8]>>(body: T) -> Self {
+        Self::new()
+            .content_type(Mime::APPLICATION_OCTET_STREAM)
+            .body(body)
+    }
+
     pub fn version(mut self, version: f32) -> Self {
         self.header.version = version;
         self
     }
 
     pub fn status(mut self, http_status: HttpStatus) -> Self {
-        self.header.status_code = http_status.into();
-        self.header.reason_phrase = http_status.to_string();
+        self.header.set_status(http_status);
         self
     }
 
@@ -86,28 +200,80 @@ impl ResponseBuilder {
         self
     }
 
-    pub fn insert_header_key_val(mut self, key: &str, val: &str) -> Self {
-        self.header.header_map.insert_by_str_key_value(key, val);
+    /// Sets the header `name`, replacing any existing values.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.header.header_map.insert(name, value);
+        self
+    }
+
+    /// Adds a value to the header `name`, keeping any existing values.
+    pub fn append_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.header.header_map.append(name, value);
+        self
+    }
+
+    /// Sets the typed header `header`, replacing any existing values.
+    pub fn typed_header<H: TypedHeader>(mut self, header: H) -> Self {
+        self.header.header_map.typed_insert(header);
         self
     }
 
+    /// Like `header`, failing if `key` or `val` is not a valid header name or value.
+    pub fn insert_header_key_val(mut self, key: &str, val: &str) -> Result<Self> {
+        self.header.header_map.insert_by_str_key_value(key, val)?;
+        Ok(self)
+    }
+
+    pub fn content_type(self, mime: Mime) -> Self {
+        self.header(HeaderName::CONTENT_TYPE, mime.into())
+    }
+
+    pub fn etag(self, etag: &EntityTag) -> Self {
+        self.header(HeaderName::ETAG, etag.into())
+    }
+
+    pub fn last_modified(self, last_modified: HttpDate) -> Self {
+        self.header(HeaderName::LAST_MODIFIED, last_modified.into())
+    }
+
+    pub fn expires(self, expires: HttpDate) -> Self {
+        self.header(HeaderName::EXPIRES, expires.into())
+    }
+
     pub fn body<T: AsRef<[u8]>>(mut self, body: T) -> Self {
         let body_len = body.as_ref().len();
 
         self.header
             .header_map
-            .insert_by_str_key_value("Content-Length", &body_len.to_string());
+            .insert(HeaderName::CONTENT_LENGTH, body_len.into());
 
         self.body = Some(Body::new(body));
         self
     }
 
+    /// Sends the body read from `reader` with `Transfer-Encoding: chunked` instead of holding it
+    /// in memory, for bodies that are large or produced gradually.
+    pub fn stream<R: Read + Send + 'static>(mut self, reader: R) -> Self {
+        self.header.header_map.remove("content-length");
+        self.header.header_map.insert(
+            HeaderName::TRANSFER_ENCODING,
+            HeaderValue::from_static("chunked"),
+        );
+
+        self.body = Some(Body::stream(reader));
+        self
+    }
+
     pub fn build(mut self) -> Response {
         let (header, body) = if let Some(body) = self.body {
             (self.header, body)
         } else {
-            // If the Status Code is not 204/No Content then we set the Content-Length header to 0.
-            if self.header.status_code != HttpStatus::NoContent.into() {
+            // If the Status Code is not 204/No Content or 304/Not Modified then we set the
+            // Content-Length header to 0.
+            if ![HttpStatus::NoContent, HttpStatus::NotModified]
+                .map(u16::from)
+                .contains(&self.header.status_code)
+            {
                 self = self.body(Body::empty());
             }
 
@@ -149,6 +315,19 @@ impl HttpItem for Response {
 
         Ok(bytes)
     }
+
+    /// Writes the response, sending a streamed body in chunks as it is read.
+    fn write_to<T: Write>(&mut self, writer: &mut T) -> Result<()> {
+        writer.write_all(&self.as_bytes()?)?;
+
+        if let Some(mut stream) = self.body.take_stream() {
+            write_chunked(&mut stream, writer)?;
+        }
+
+        writer.flush()?;
+
+        Ok(())
+    }
 }
 
 impl Response {
@@ -184,6 +363,17 @@ impl std::default::Default for ResponseHeader {
     }
 }
 
+impl ResponseHeader {
+    pub fn header_map_mut(&mut self) -> &mut HeaderMap {
+        &mut self.header_map
+    }
+
+    pub fn set_status(&mut self, http_status: HttpStatus) {
+        self.status_code = http_status.into();
+        self.reason_phrase = http_status.to_string();
+    }
+}
+
 impl FromStr for ResponseHeader {
     type Err = HttpInternalError;
 
@@ -232,7 +422,130 @@ impl HeaderItem for ResponseHeader {
 mod tests {
     use std::str::FromStr;
 
-    use super::ResponseHeader;
+    use crate::error::HttpError;
+    use crate::header_item::HeaderItem;
+    use crate::header_map::HeaderMap;
+    use crate::header_name::HeaderName;
+    use crate::header_value::HeaderValue;
+    use crate::http_date::HttpDate;
+    use crate::http_status::HttpStatus;
+
+    use crate::mime::Mime;
+
+    use super::{HttpResponse, Response, ResponseBuilder, ResponseHeader};
+
+    struct UserGone;
+
+    impl From<UserGone> for HttpError {
+        fn from(_: UserGone) -> Self {
+            HttpError::gone("User has been deleted.")
+        }
+    }
+
+    fn respond<T: HttpResponse + 'static>(r: T) -> Response {
+        Box::new(r).into_response()
+    }
+
+    #[test]
+    fn content_types() {
+        let content_type = |res: &Response| {
+            res.header
+                .header_map()
+                .get_by_str_key("content-type")
+                .map(|c| c.to_owned())
+        };
+
+        let res = ResponseBuilder::html("<h1>Hi</h1>").build();
+        assert_eq!(
+            content_type(&res).as_deref(),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(res.body.contents, b"<h1>Hi</h1>");
+
+        let res = ResponseBuilder::json_str(r#"{"a":1}"#).build();
+        assert_eq!(content_type(&res).as_deref(), Some("application/json"));
+
+        let res = ResponseBuilder::new()
+            .content_type(Mime::IMAGE_PNG)
+            .body([137, 80, 78, 71])
+            .build();
+        assert_eq!(content_type(&res).as_deref(), Some("image/png"));
+
+        let res = respond("Hello");
+        assert_eq!(
+            content_type(&res).as_deref(),
+            Some("text/plain; charset=utf-8")
+        );
+
+        let res = respond(vec![1_u8, 2, 3]);
+        assert_eq!(
+            content_type(&res).as_deref(),
+            Some("application/octet-stream")
+        );
+
+        let res = respond(());
+        assert_eq!(content_type(&res), None);
+    }
+
+    #[test]
+    fn natural_responses() {
+        let res = respond(format!("Hello {}", "Zak"));
+        assert_eq!(
+            (res.header.status_code, res.body.contents),
+            (200, b"Hello Zak".to_vec())
+        );
+
+        let res = respond(None::<String>);
+        assert_eq!(res.header.status_code, 404);
+
+        let res = respond((HttpStatus::Created, "Made"));
+        assert_eq!(res.header.status_code, 201);
+        assert_eq!(res.header.reason_phrase, "Created");
+        assert_eq!(res.body.contents, b"Made");
+
+        let mut header_map = HeaderMap::default();
+        header_map.insert(HeaderName::LOCATION, HeaderValue::from_static("/users/1"));
+
+        let res = respond((HttpStatus::SeeOther, header_map, ()));
+        assert_eq!(res.header.status_code, 303);
+        assert_eq!(
+            res.header.header_map().get_by_str_key("location"),
+            Some("/users/1")
+        );
+
+        let res = respond(Err::<String, _>(UserGone));
+        assert_eq!(res.header.status_code, 410);
+
+        let boxed: Box<dyn HttpResponse> = Box::new(Some("Boxed"));
+        let res = respond(boxed);
+        assert_eq!(res.body.contents, b"Boxed");
+    }
+
+    #[test]
+    fn error_response() {
+        let res: Result<&'static str, HttpError> = Err(HttpError::not_found("Missing").header(
+            HeaderName::from_static("X-Reason"),
+            HeaderValue::from_static("gone fishing"),
+        ));
+
+        let res = Box::new(res).into_response();
+
+        assert_eq!(res.header.status_code, 404);
+        assert_eq!(res.header.reason_phrase, "Not Found");
+        assert_eq!(
+            res.header.header_map().get_by_str_key("x-reason"),
+            Some("gone fishing")
+        );
+        assert_eq!(res.body.contents, b"Missing");
+
+        let res = Box::new(HttpError::conflict("Taken").problem()).into_response();
+
+        assert_eq!(res.header.status_code, 409);
+        assert_eq!(
+            res.header.header_map().get_by_str_key("content-type"),
+            Some("application/problem+json")
+        );
+    }
 
     #[test]
     fn read_response() {
@@ -257,6 +570,7 @@ mod tests {
             headers.get_by_str_key("date"),
             Some("Mon, 23 May 2005 22:38:34 GMT")
         );
+        assert_eq!(headers.date(), Some(HttpDate::from_unix_secs(1116887914)));
         assert_eq!(
             headers.get_by_str_key("content-type"),
             Some("text/html; charset=UTF-8")
@@ -265,6 +579,10 @@ mod tests {
             headers.get_by_str_key("last-modified"),
             Some("Wed, 08 Jan 2003 23:11:55 GMT")
         );
+        assert_eq!(
+            headers.last_modified().map(|d| d.to_string()).as_deref(),
+            Some("Wed, 08 Jan 2003 23:11:55 GMT")
+        );
         assert_eq!(
             headers.get_by_str_key("server"),
             Some("Apache/1.3.3.7 (Unix) (Red-Hat/Linux)")
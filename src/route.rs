@@ -1,15 +1,18 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use crate::{
-    make_handler, method::Method, request::ServerRequest, response::HttpResponse, server::Server,
+    handler::{self, Handler, HandlerFn},
+    make_handler,
+    method::Method,
+    server::Server,
 };
 
-type HandlerFn = dyn Fn(ServerRequest) -> Box<dyn HttpResponse + Send + Sync> + Send + Sync;
-type RouteHandlers = HashMap<Method, Box<HandlerFn>>;
+pub(crate) type RouteHandlers = HashMap<Method, Box<HandlerFn>>;
 
 #[derive(Default)]
 pub struct RouteMap {
     pub all_routes: Vec<(RouteKey, RouteHandlers)>,
+    timeouts: Vec<(String, Duration)>,
 }
 
 impl RouteMap {
@@ -17,14 +20,26 @@ impl RouteMap {
         Self::default()
     }
 
+    /// The route matching the request path `key`. When several match, the most specific wins:
+    /// going through the segments in order, a literal segment beats a `{name}` and a `{name}`
+    /// beats a `{*rest}`.
     pub fn get(&self, key: &RouteKey) -> Option<&(RouteKey, RouteHandlers)> {
-        self.all_routes.iter().find(|(k, _)| k == key)
+        self.all_routes
+            .iter()
+            .filter(|(k, _)| k == key)
+            .max_by_key(|(k, _)| k.specificity())
+    }
+
+    /// The route registered as `key`, compared exactly rather than as a pattern.
+    pub fn get_exact(&self, key: &RouteKey) -> Option<&(RouteKey, RouteHandlers)> {
+        self.all_routes.iter().find(|(k, _)| k.0 == key.0)
     }
 
+    /// The handlers of the route registered as `key`, compared exactly rather than as a pattern.
     pub fn get_mut(&mut self, key: &RouteKey) -> Option<&mut RouteHandlers> {
         self.all_routes
             .iter_mut()
-            .find(|(k, _)| k == key)
+            .find(|(k, _)| k.0 == key.0)
             .map(|(_, h)| h)
     }
 
@@ -34,6 +49,19 @@ impl RouteMap {
 
         self.all_routes.push((key, h));
     }
+
+    /// The timeout of the route registered as `key`, matched exactly rather than as a pattern.
+    pub fn timeout(&self, key: &RouteKey) -> Option<Duration> {
+        self.timeouts
+            .iter()
+            .find(|(k, _)| *k == key.0)
+            .map(|(_, timeout)| *timeout)
+    }
+
+    pub fn set_timeout(&mut self, key: &str, timeout: Duration) {
+        self.timeouts.retain(|(k, _)| k != key);
+        self.timeouts.push((key.to_owned(), timeout));
+    }
 }
 
 impl std::fmt::Debug for RouteMap {
@@ -49,18 +77,51 @@ pub fn named_path_filter(s: &str) -> bool {
     s.starts_with('{') && s.ends_with('}')
 }
 
+/// A `{*name}` segment, which matches the rest of the path including any further `/`.
+pub fn catch_all_path_filter(s: &str) -> bool {
+    s.starts_with("{*") && s.ends_with('}')
+}
+
+impl RouteKey {
+    /// The rank of each segment, for choosing between routes matching the same path.
+    ///
+    /// The end of the route ranks above a catch-all, so `/x` beats `/x/{*rest}` for the path
+    /// `/x`, where the catch-all would match nothing.
+    fn specificity(&self) -> Vec<u8> {
+        self.0
+            .split('/')
+            .map(|s| {
+                if catch_all_path_filter(s) {
+                    0
+                } else if named_path_filter(s) {
+                    2
+                } else {
+                    3
+                }
+            })
+            .chain([1])
+            .collect()
+    }
+}
+
 impl std::cmp::PartialEq for RouteKey {
     fn eq(&self, other: &Self) -> bool {
-        let self_split = self.0.split('/');
-        let other_split = other.0.split('/');
-
-        if self_split.clone().count() != other_split.clone().count() {
-            false
-        } else {
-            self_split
-                .zip(other_split)
-                .filter(|(s, o)| !named_path_filter(s) && !named_path_filter(o))
-                .all(|(s, o)| s.eq_ignore_ascii_case(o))
+        let mut self_split = self.0.split('/');
+        let mut other_split = other.0.split('/');
+
+        loop {
+            match (self_split.next(), other_split.next()) {
+                (Some(s), _) if catch_all_path_filter(s) => return true,
+                (_, Some(o)) if catch_all_path_filter(o) => return true,
+                (Some(s), Some(o)) => {
+                    if !named_path_filter(s) && !named_path_filter(o) && !s.eq_ignore_ascii_case(o)
+                    {
+                        return false;
+                    }
+                }
+                (None, None) => return true,
+                _ => return false,
+            }
         }
     }
 }
@@ -76,6 +137,13 @@ impl<'a> Route<'a> {
         Self { server, uri }
     }
 
+    /// Responds with `503 Service Unavailable` if a request to this route takes longer than
+    /// `timeout`, overriding `Server::timeout`.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.server.routes.set_timeout(self.uri, timeout);
+        self
+    }
+
     make_handler!(get, Method::GET);
     make_handler!(head, Method::HEAD);
     make_handler!(post, Method::POST);
@@ -90,15 +158,14 @@ impl<'a> Route<'a> {
 #[macro_export]
 macro_rules! make_handler {
     ($name: ident, $method: path) => {
-        pub fn $name<F, R>(self, handler: F) -> Self
+        pub fn $name<H, Args>(self, handler: H) -> Self
         where
-            F: Fn(ServerRequest) -> R + Send + Sync + 'static,
-            R: HttpResponse + Send + Sync + 'static,
+            H: Handler<Args>,
+            Args: 'static,
         {
             let uri = RouteKey(self.uri.to_owned());
 
-            let h =
-                Box::new(move |req| Box::new(handler(req)) as Box<dyn HttpResponse + Send + Sync>);
+            let h = handler::boxed(handler);
 
             if let Some(handlers) = self.server.routes.get_mut(&uri) {
                 handlers.insert($method, h);
@@ -147,4 +214,43 @@ mod tests {
         assert_ne!(route_3, request_uri_2);
         assert_eq!(route_3, request_uri_3);
     }
+
+    #[test]
+    fn test_catch_all_eq() {
+        let route = RouteKey("/assets/{*path}".to_owned());
+
+        assert_eq!(route, RouteKey("/assets/app.js".to_owned()));
+        assert_eq!(route, RouteKey("/assets/img/logo.png".to_owned()));
+        assert_eq!(route, RouteKey("/assets/".to_owned()));
+        assert_eq!(route, RouteKey("/assets".to_owned()));
+        assert_ne!(route, RouteKey("/other/app.js".to_owned()));
+        assert_ne!(route, RouteKey("/".to_owned()));
+    }
+
+    #[test]
+    fn test_most_specific_route() {
+        let mut server = Server::new([127, 0, 0, 1], 1234);
+
+        server.at("/{*path}").get(|| "catch all");
+        server.at("/api/users").get(|| "users");
+        server.at("/api/{name}").get(|| "named");
+        server.at("/api/users").post(|| "created");
+        server.at("/files").get(|| "files");
+        server.at("/files/{*rest}").get(|| "file");
+
+        let routes = &server.routes;
+        assert_eq!(routes.all_routes.len(), 5);
+
+        let route = |path: &str| {
+            routes
+                .get(&RouteKey(path.to_owned()))
+                .map(|(k, h)| (k.0.as_str(), h.len()))
+        };
+
+        assert_eq!(route("/api/users"), Some(("/api/users", 2)));
+        assert_eq!(route("/api/zak"), Some(("/api/{name}", 1)));
+        assert_eq!(route("/other/page"), Some(("/{*path}", 1)));
+        assert_eq!(route("/files"), Some(("/files", 1)));
+        assert_eq!(route("/files/a/b"), Some(("/files/{*rest}", 1)));
+    }
 }
@@ -1,24 +1,46 @@
 use std::{
+    cell::RefCell,
     io::{BufReader, BufWriter, Read, Write},
-    net::{Ipv4Addr, SocketAddrV4, TcpListener, TcpStream},
-    sync::Arc,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread,
     time::Duration,
 };
 
 use crate::{
-    error::HttpInternalError,
+    conditional::Preconditions,
+    deadline::Deadline,
+    error::{HttpError, HttpInternalError},
+    extensions::Extensions,
+    handler::{self, Handler, HandlerFn},
+    header_item::HeaderItem,
+    header_name::HeaderName,
+    header_value::HeaderValue,
+    http_date::HttpDate,
     http_item::HttpItem,
     http_status::HttpStatus,
+    middleware::{Middleware, Next},
     pool::ThreadPool,
     request::{Request, ServerRequest},
-    response::ResponseBuilder,
-    route::{Route, RouteKey, RouteMap},
+    response::{HttpResponse, Response, ResponseBuilder},
+    route::{Route, RouteHandlers, RouteKey, RouteMap},
+    typed_header::Allow,
     Result,
 };
 
 pub struct Server {
     address: SocketAddrV4,
     pub(crate) routes: RouteMap,
+    state: Arc<Extensions>,
+    middleware: Vec<Box<dyn Middleware>>,
+    fallback: Option<Box<HandlerFn>>,
+    method_not_allowed: Option<Box<HandlerFn>>,
+    timeout: Option<Duration>,
+    max_timed_requests: usize,
+    timed_requests: Arc<AtomicUsize>,
 }
 
 impl std::fmt::Debug for Server {
@@ -38,25 +60,100 @@ impl Server {
 
         let routes = RouteMap::new();
 
-        Self { address, routes }
+        Self {
+            address,
+            routes,
+            state: Arc::default(),
+            middleware: Vec::new(),
+            fallback: None,
+            method_not_allowed: None,
+            timeout: None,
+            max_timed_requests: 1024,
+            timed_requests: Arc::default(),
+        }
+    }
+
+    /// Makes `state` available to every handler through `ServerRequest::state`.
+    ///
+    /// State is looked up by type, so several values of different types can be registered.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a request still holds the existing state, which can only happen while a handler
+    /// is running.
+    pub fn with_state<T: Send + Sync + 'static>(&mut self, state: T) -> &mut Self {
+        Arc::get_mut(&mut self.state)
+            .expect("State cannot be added while requests are being handled.")
+            .insert(state);
+
+        self
     }
 
-    pub fn at(&mut self, location: &'static str) -> Route {
+    pub fn at(&mut self, location: &'static str) -> Route<'_> {
         Route::new(self, location)
     }
 
+    /// Adds a middleware to the chain. Middleware runs in the order it is added, before the
+    /// route handler and for every request, including those that do not match a route.
+    pub fn wrap<M: Middleware>(&mut self, middleware: M) -> &mut Self {
+        self.middleware.push(Box::new(middleware));
+        self
+    }
+
+    /// Sets the handler for requests that do not match any route, replacing the empty
+    /// `404 Not Found` response.
+    pub fn fallback<H, Args>(&mut self, handler: H) -> &mut Self
+    where
+        H: Handler<Args>,
+        Args: 'static,
+    {
+        self.fallback = Some(handler::boxed(handler));
+        self
+    }
+
+    /// Sets the handler for requests that match a route but not any of its methods, replacing the
+    /// empty `405 Method Not Allowed` response. The `Allow` header is added if the handler does
+    /// not set it.
+    pub fn method_not_allowed<H, Args>(&mut self, handler: H) -> &mut Self
+    where
+        H: Handler<Args>,
+        Args: 'static,
+    {
+        self.method_not_allowed = Some(handler::boxed(handler));
+        self
+    }
+
+    /// Responds with `503 Service Unavailable` to requests that take longer than `timeout`, so
+    /// slow handlers do not hold up a worker. Routes can override it with `Route::timeout`.
+    ///
+    /// The handlers of requests with a timeout run on a thread of their own, which keeps running
+    /// after the timeout. Handlers can read the `Deadline` of the request to stop early. The
+    /// timeout response still passes back through the middleware.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The most handlers of requests with a timeout running at once, counting those still running
+    /// after their timeout. Further requests get `503 Service Unavailable` straight away.
+    /// Defaults to 1024.
+    pub fn max_timed_requests(&mut self, max_timed_requests: usize) -> &mut Self {
+        self.max_timed_requests = max_timed_requests;
+        self
+    }
+
     pub fn start(self) -> Result<()> {
         let listener = TcpListener::bind(self.address)?;
 
         let mut pool = ThreadPool::new()?;
 
-        let routes = Arc::new(self.routes);
+        let server = Arc::new(self);
 
         for stream in listener.incoming() {
-            let routes = routes.clone();
+            let server = server.clone();
 
             pool.spawn(move || {
-                if let Err(e) = Self::handle_connection(stream, &*routes) {
+                if let Err(e) = server.handle_connection(stream) {
                     eprintln!("{}", e);
                 }
             });
@@ -65,9 +162,127 @@ impl Server {
         Ok(())
     }
 
+    /// Responds to `req` through the middleware, giving the handler a `Deadline` if the request
+    /// has a timeout.
+    fn respond(self: &Arc<Self>, req: Request, peer_address: SocketAddr) -> Response {
+        let uri = RouteKey(req.header.path().to_owned());
+
+        let route = self.routes.get(&uri);
+
+        let route_key = route.map(|(k, _)| k.clone()).unwrap_or(uri);
+
+        let deadline = route
+            .and_then(|(k, _)| self.routes.timeout(k))
+            .or(self.timeout)
+            .map(Deadline::after);
+
+        let method = req.header.method;
+        let preconditions = Preconditions::from_headers(req.header.header_map());
+
+        let mut server_req =
+            ServerRequest::new(route_key, req, peer_address).with_state(self.state.clone());
+
+        if let Some(deadline) = deadline {
+            server_req.extensions_mut().insert(deadline);
+        }
+
+        let endpoint = |req: ServerRequest| match deadline {
+            Some(deadline) => self.dispatch_in_time(route.map(|(k, _)| k), req, deadline),
+            None => self.dispatch(route, req),
+        };
+
+        let res = Next::new(&self.middleware, &endpoint).run(server_req);
+
+        let mut res = preconditions.apply(&method, res);
+
+        let header_map = res.header.header_map_mut();
+
+        if header_map.get_by_str_key("date").is_none() {
+            header_map.insert(HeaderName::DATE, current_date());
+        }
+
+        res
+    }
+
+    /// Calls the handler of `route`, or the fallback handlers if there is no route or it has no
+    /// handler for the request method.
+    fn dispatch(&self, route: Option<&(RouteKey, RouteHandlers)>, req: ServerRequest) -> Response {
+        if let Some((_, route_handlers)) = route {
+            if let Some(handler) = route_handlers.get(&req.request.header.method) {
+                (handler)(req)
+            } else {
+                let mut res = if let Some(handler) = &self.method_not_allowed {
+                    (handler)(req)
+                } else {
+                    ResponseBuilder::new()
+                        .status(HttpStatus::MethodNotAllowed)
+                        .build()
+                };
+
+                let header_map = res.header.header_map_mut();
+
+                if header_map.get_by_str_key("allow").is_none() {
+                    let mut allowed = route_handlers.keys().copied().collect::<Vec<_>>();
+
+                    allowed.sort_by_key(|m| m.to_string());
+
+                    header_map.typed_insert(Allow(allowed));
+                }
+
+                res
+            }
+        } else if let Some(handler) = &self.fallback {
+            (handler)(req)
+        } else {
+            ResponseBuilder::new().status(HttpStatus::NotFound).build()
+        }
+    }
+
+    /// Calls the handler of the route registered as `route_key` on a thread of its own, giving up
+    /// with `503 Service Unavailable` if `deadline` passes first.
+    fn dispatch_in_time(
+        self: &Arc<Self>,
+        route_key: Option<&RouteKey>,
+        req: ServerRequest,
+        deadline: Deadline,
+    ) -> Response {
+        let unavailable =
+            |message: &str| Box::new(HttpError::service_unavailable(message)).into_response();
+
+        let Some(in_flight) = InFlight::start(&self.timed_requests, self.max_timed_requests) else {
+            return unavailable("Too many requests are in progress.");
+        };
+
+        let (tx, rx) = mpsc::channel();
+
+        let server = self.clone();
+        let route_key = route_key.cloned();
+
+        let spawned = thread::Builder::new().spawn(move || {
+            let _in_flight = in_flight;
+
+            let route = route_key.and_then(|k| server.routes.get_exact(&k));
+
+            let _ = tx.send(server.dispatch(route, req));
+        });
+
+        if spawned.is_err() {
+            return unavailable("Too many requests are in progress.");
+        }
+
+        match rx.recv_timeout(deadline.remaining()) {
+            Ok(res) => res,
+            Err(mpsc::RecvTimeoutError::Timeout) => unavailable("The request timed out."),
+            Err(mpsc::RecvTimeoutError::Disconnected) => Box::new(
+                HttpError::internal_server_error("The request handler panicked."),
+            )
+            .into_response(),
+        }
+    }
+
     fn handle_connection(
+        self: &Arc<Self>,
         stream: std::result::Result<TcpStream, std::io::Error>,
-        routes: &RouteMap,
     ) -> Result<()> {
         let stream = stream?;
 
@@ -86,23 +301,7 @@ impl Server {
         loop {
             match Request::from_stream(read_buf.by_ref()) {
                 Ok(req) => {
-                    let uri = RouteKey(req.header.uri.to_owned());
-
-                    let response = if let Some((route_key, route_handlers)) = routes.get(&uri) {
-                        if let Some(handler) = route_handlers.get(&req.header.method) {
-                            let server_req =
-                                ServerRequest::new(route_key.clone(), req, peer_address);
-                            let res = (handler)(server_req);
-
-                            res.into_response()
-                        } else {
-                            ResponseBuilder::new()
-                                .status(HttpStatus::MethodNotAllowed)
-                                .build()
-                        }
-                    } else {
-                        ResponseBuilder::new().status(HttpStatus::NotFound).build()
-                    };
+                    let mut response = self.respond(req, peer_address);
 
                     response.write_to(write_buf.by_ref())?;
                 }
@@ -122,9 +321,53 @@ impl Server {
     }
 }
 
+/// Counts a handler of a timed request as running until it is dropped.
+struct InFlight(Arc<AtomicUsize>);
+
+impl InFlight {
+    fn start(count: &Arc<AtomicUsize>, max: usize) -> Option<Self> {
+        count
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .ok()
+            .map(|_| Self(count.clone()))
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+thread_local! {
+    static CURRENT_DATE: RefCell<(u64, HeaderValue)> =
+        const { RefCell::new((0, HeaderValue::from_static(""))) };
+}
+
+/// The current time formatted for the `Date` header, formatted at most once per second on each
+/// worker thread.
+fn current_date() -> HeaderValue {
+    CURRENT_DATE.with(|cached| {
+        let mut cached = cached.borrow_mut();
+
+        let now = HttpDate::now();
+
+        if cached.0 != now.unix_secs() {
+            *cached = (now.unix_secs(), now.into());
+        }
+
+        cached.1.clone()
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::response::ResponseBuilder;
+    use crate::{
+        conditional::EntityTag, cors::Cors, method::Method, request::RequestBuilder,
+        response::ResponseBuilder, static_files::StaticFiles,
+    };
 
     use super::*;
 
@@ -133,7 +376,339 @@ mod tests {
         let mut s = Server::new([127, 0, 0, 1], 1234);
 
         s.at("/hello")
-            .get(|_| ResponseBuilder::new().build())
-            .connect(|_| ResponseBuilder::new().build());
+            .get(|_: ServerRequest| ResponseBuilder::new().build())
+            .connect(|_: ServerRequest| ResponseBuilder::new().build());
+    }
+
+    #[derive(Debug)]
+    struct AppState {
+        greeting: &'static str,
+    }
+
+    #[test]
+    fn test_state_and_middleware() {
+        let mut s = Server::new([127, 0, 0, 1], 1234);
+
+        s.with_state(AppState { greeting: "Hi" });
+
+        s.wrap(|mut req: ServerRequest, next: Next<'_>| {
+            req.extensions_mut().insert("Zak");
+            next.run(req)
+        });
+
+        s.at("/hello").get(|req: ServerRequest| {
+            let greeting = req.state::<AppState>().unwrap().greeting;
+            let name = req.extensions().get::<&str>().unwrap();
+
+            ResponseBuilder::new()
+                .body(format!("{} {}", greeting, name))
+                .build()
+        });
+
+        let s = Arc::new(s);
+
+        let peer = SocketAddr::from(([127, 0, 0, 1], 4321));
+
+        let res = s.respond(RequestBuilder::new().uri("/hello").build(), peer);
+        assert_eq!(res.body.contents, b"Hi Zak");
+        assert!(res.header.header_map().date().is_some());
+
+        let res = s.respond(RequestBuilder::new().uri("/nope").build(), peer);
+        assert_eq!(res.header.status_code, 404);
+    }
+
+    #[test]
+    fn test_catch_all_route() {
+        let mut s = Server::new([127, 0, 0, 1], 1234);
+
+        s.at("/files/{*path}")
+            .get(|req: ServerRequest| req.path::<String>("path"));
+
+        s.at("/assets/{*path}").get(StaticFiles::new("."));
+
+        s.at("/files/readme").get(|| "Readme");
+
+        let s = Arc::new(s);
+
+        let peer = SocketAddr::from(([127, 0, 0, 1], 4321));
+
+        let res = s.respond(RequestBuilder::new().uri("/files/a/b.txt").build(), peer);
+        assert_eq!(res.body.contents, b"a/b.txt");
+
+        let res = s.respond(RequestBuilder::new().uri("/files/readme").build(), peer);
+        assert_eq!(res.body.contents, b"Readme");
+
+        let res = s.respond(
+            RequestBuilder::new().uri("/assets/Cargo.toml").build(),
+            peer,
+        );
+        assert_eq!(res.body.contents, std::fs::read("Cargo.toml").unwrap());
+    }
+
+    #[test]
+    fn test_fallback_handlers() {
+        let mut s = Server::new([127, 0, 0, 1], 1234);
+
+        s.at("/hello")
+            .get(|| "Hello")
+            .post(|| "Posted")
+            .delete(|| "Deleted");
+
+        let mut s = Arc::new(s);
+
+        let peer = SocketAddr::from(([127, 0, 0, 1], 4321));
+
+        let res = s.respond(
+            RequestBuilder::new()
+                .method(Method::PUT)
+                .uri("/hello")
+                .build(),
+            peer,
+        );
+        assert_eq!(res.header.status_code, 405);
+        assert_eq!(
+            res.header.header_map().get_by_str_key("allow"),
+            Some("DELETE, GET, POST")
+        );
+
+        Arc::get_mut(&mut s)
+            .unwrap()
+            .fallback(|| {
+                ResponseBuilder::new()
+                    .status(HttpStatus::NotFound)
+                    .body(r#"{"error":"not found"}"#)
+                    .build()
+            })
+            .method_not_allowed(|| {
+                ResponseBuilder::new()
+                    .status(HttpStatus::MethodNotAllowed)
+                    .body(r#"{"error":"method not allowed"}"#)
+                    .build()
+            });
+
+        let res = s.respond(RequestBuilder::new().uri("/nope").build(), peer);
+        assert_eq!(res.header.status_code, 404);
+        assert_eq!(res.body.contents, br#"{"error":"not found"}"#);
+
+        let res = s.respond(
+            RequestBuilder::new()
+                .method(Method::PUT)
+                .uri("/hello")
+                .build(),
+            peer,
+        );
+        assert_eq!(res.header.status_code, 405);
+        assert_eq!(res.body.contents, br#"{"error":"method not allowed"}"#);
+        assert!(res.header.header_map().get_by_str_key("allow").is_some());
+    }
+
+    #[test]
+    fn test_conditional_requests() {
+        let mut s = Server::new([127, 0, 0, 1], 1234);
+
+        s.at("/report")
+            .get(|| {
+                ResponseBuilder::text("Report")
+                    .etag(&EntityTag::strong("v1"))
+                    .build()
+            })
+            .put(|req: ServerRequest| {
+                req.check_preconditions(Some(&EntityTag::strong("v1")), None)
+                    .map(|_| "Updated")
+            });
+
+        let s = Arc::new(s);
+
+        let peer = SocketAddr::from(([127, 0, 0, 1], 4321));
+
+        let get = |etag: &str| {
+            RequestBuilder::new()
+                .uri("/report")
+                .insert_header_key_val("If-None-Match", etag)
+                .unwrap()
+                .build()
+        };
+
+        let res = s.respond(get("\"v1\""), peer);
+        assert_eq!(res.header.status_code, 304);
+        assert!(res.body.contents.is_empty());
+
+        let res = s.respond(get("\"v0\""), peer);
+        assert_eq!(res.body.contents, b"Report");
+
+        let put = |etag: &str| {
+            RequestBuilder::new()
+                .method(Method::PUT)
+                .uri("/report")
+                .insert_header_key_val("If-Match", etag)
+                .unwrap()
+                .build()
+        };
+
+        assert_eq!(s.respond(put("\"v1\""), peer).body.contents, b"Updated");
+        assert_eq!(s.respond(put("\"v0\""), peer).header.status_code, 412);
+    }
+
+    #[test]
+    fn test_content_negotiation() {
+        let mut s = Server::new([127, 0, 0, 1], 1234);
+
+        s.at("/user").get(|req: ServerRequest| {
+            req.negotiate(&["application/json", "text/html"])
+                .map(|m| match m {
+                    "text/html" => ResponseBuilder::html("<p>Zak</p>").build(),
+                    _ => ResponseBuilder::json_str(r#"{"name":"Zak"}"#).build(),
+                })
+        });
+
+        let s = Arc::new(s);
+
+        let peer = SocketAddr::from(([127, 0, 0, 1], 4321));
+
+        let get = |accept: &str| {
+            s.respond(
+                RequestBuilder::new()
+                    .uri("/user")
+                    .insert_header_key_val("Accept", accept)
+                    .unwrap()
+                    .build(),
+                peer,
+            )
+        };
+
+        assert_eq!(get("text/html,*/*;q=0.8").body.contents, b"<p>Zak</p>");
+        assert_eq!(get("application/*").body.contents, br#"{"name":"Zak"}"#);
+        assert_eq!(get("image/png").header.status_code, 406);
+    }
+
+    #[test]
+    fn test_cors_preflight() {
+        let mut s = Server::new([127, 0, 0, 1], 1234);
+
+        s.wrap(
+            Cors::new()
+                .allow_origin("https://app.example.com")
+                .allow_methods([Method::PUT]),
+        );
+
+        s.at("/report").put(|_: ServerRequest| "Updated");
+
+        let s = Arc::new(s);
+
+        let peer = SocketAddr::from(([127, 0, 0, 1], 4321));
+
+        let req = |method: Method| {
+            RequestBuilder::new()
+                .method(method)
+                .uri("/report")
+                .insert_header_key_val("Origin", "https://app.example.com")
+                .unwrap()
+                .insert_header_key_val("Access-Control-Request-Method", "PUT")
+                .unwrap()
+                .build()
+        };
+
+        let res = s.respond(req(Method::OPTIONS), peer);
+        assert_eq!(res.header.status_code, 204);
+        assert_eq!(
+            res.header
+                .header_map()
+                .get_by_str_key("access-control-allow-methods"),
+            Some("PUT")
+        );
+
+        let res = s.respond(req(Method::PUT), peer);
+        assert_eq!(res.body.contents, b"Updated");
+        assert_eq!(
+            res.header
+                .header_map()
+                .get_by_str_key("access-control-allow-origin"),
+            Some("https://app.example.com")
+        );
+    }
+
+    #[test]
+    fn test_request_timeouts() {
+        let mut s = Server::new([127, 0, 0, 1], 1234);
+
+        s.timeout(Duration::from_millis(50));
+
+        s.wrap(|req: ServerRequest, next: Next<'_>| {
+            let mut res = next.run(req);
+
+            res.header.header_map_mut().insert(
+                HeaderName::from_static("X-Wrapped"),
+                HeaderValue::from_static("1"),
+            );
+
+            res
+        });
+
+        s.at("/slow").get(|deadline: Deadline| {
+            while !deadline.is_expired() {
+                thread::sleep(Duration::from_millis(5));
+            }
+
+            "Done"
+        });
+
+        s.at("/patient")
+            .timeout(Duration::from_secs(5))
+            .get(|req: ServerRequest| {
+                thread::sleep(Duration::from_millis(100));
+
+                let remaining = req.deadline().unwrap().remaining();
+                assert!(remaining > Duration::from_secs(4));
+
+                "Done"
+            });
+
+        s.at("/fast").get(|| "Done");
+
+        s.at("/panic")
+            .get(|| -> &'static str { panic!("Handler failed.") });
+
+        let s = Arc::new(s);
+
+        let peer = SocketAddr::from(([127, 0, 0, 1], 4321));
+
+        let get =
+            |s: &Arc<Server>, uri: &str| s.respond(RequestBuilder::new().uri(uri).build(), peer);
+
+        let res = get(&s, "/slow");
+        assert_eq!(res.header.status_code, 503);
+        assert!(res.header.header_map().date().is_some());
+        assert_eq!(
+            res.header.header_map().get_by_str_key("x-wrapped"),
+            Some("1")
+        );
+
+        let res = get(&s, "/panic");
+        assert_eq!(res.header.status_code, 500);
+        assert_eq!(
+            res.header.header_map().get_by_str_key("x-wrapped"),
+            Some("1")
+        );
+
+        assert_eq!(get(&s, "/patient").body.contents, b"Done");
+        assert_eq!(get(&s, "/fast").body.contents, b"Done");
+        assert_eq!(get(&s, "/nope").header.status_code, 404);
+
+        let mut s = Server::new([127, 0, 0, 1], 1234);
+
+        s.timeout(Duration::from_secs(5)).max_timed_requests(0);
+
+        s.at("/fast").get(|| "Done");
+
+        let res = get(&Arc::new(s), "/fast");
+        assert_eq!(res.header.status_code, 503);
+        assert!(res.header.header_map().date().is_some());
+
+        let mut s = Server::new([127, 0, 0, 1], 1234);
+
+        s.at("/slow").get(|_: Deadline| "Done");
+
+        let res = get(&Arc::new(s), "/slow");
+        assert_eq!(res.header.status_code, 500);
     }
 }
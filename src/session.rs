@@ -0,0 +1,458 @@
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    cookie::{Cookie, SameSite},
+    crypto,
+    error::HttpError,
+    extract::FromRequest,
+    header_name::HeaderName,
+    middleware::{Middleware, Next},
+    request::ServerRequest,
+    response::Response,
+};
+
+/// The values stored in a session.
+pub type SessionData = HashMap<String, String>;
+
+/// Where the `Sessions` middleware keeps session data, keyed by session id.
+pub trait SessionStore: Send + Sync + 'static {
+    /// The data of an unexpired session.
+    fn load(&self, id: &str) -> Option<SessionData>;
+
+    /// Saves the data of a session, replacing any previous data. The session expires after
+    /// `ttl` unless it is saved again.
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration);
+
+    fn remove(&self, id: &str);
+}
+
+/// Keeps sessions in memory, so they are lost on restart and not shared between servers.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    sessions: Mutex<HashMap<String, (SessionData, Instant)>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions().is_empty()
+    }
+
+    fn sessions(&self) -> MutexGuard<'_, HashMap<String, (SessionData, Instant)>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl SessionStore for MemoryStore {
+    fn load(&self, id: &str) -> Option<SessionData> {
+        let mut sessions = self.sessions();
+
+        match sessions.get(id) {
+            Some((_, expires)) if *expires <= Instant::now() => {
+                sessions.remove(id);
+                None
+            }
+            Some((data, _)) => Some(data.clone()),
+            None => None,
+        }
+    }
+
+    /// Expired sessions are removed whenever a session is saved.
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) {
+        let now = Instant::now();
+
+        let mut sessions = self.sessions();
+
+        sessions.retain(|_, (_, expires)| *expires > now);
+        sessions.insert(id.to_owned(), (data.clone(), now + ttl));
+    }
+
+    fn remove(&self, id: &str) {
+        self.sessions().remove(id);
+    }
+}
+
+#[derive(Debug, Default)]
+struct SessionState {
+    id: Option<String>,
+    data: SessionData,
+    changed: bool,
+    renew: bool,
+    destroyed: bool,
+}
+
+/// The session of the current request, available to handlers through `ServerRequest::session`
+/// or as an extractor when the `Sessions` middleware is used.
+///
+/// Changes are saved after the handler returns. Cloning a `Session` gives another handle to the
+/// same session.
+#[derive(Debug, Clone, Default)]
+pub struct Session(Arc<Mutex<SessionState>>);
+
+impl Session {
+    fn state(&self) -> MutexGuard<'_, SessionState> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The session id, or `None` for a new session that has not been saved yet.
+    pub fn id(&self) -> Option<String> {
+        self.state().id.clone()
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.state().data.get(key).cloned()
+    }
+
+    pub fn get_as<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key).and_then(|v| v.parse().ok())
+    }
+
+    pub fn insert<K: Into<String>, V: ToString>(&self, key: K, value: V) {
+        let mut state = self.state();
+
+        state.data.insert(key.into(), value.to_string());
+        state.changed = true;
+    }
+
+    pub fn remove(&self, key: &str) -> Option<String> {
+        let mut state = self.state();
+
+        let value = state.data.remove(key);
+        state.changed |= value.is_some();
+
+        value
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state().data.is_empty()
+    }
+
+    /// Moves the session to a new id, keeping its data. Call this when a user logs in, so that
+    /// an id planted before login cannot be used to take over the session.
+    pub fn renew(&self) {
+        self.state().renew = true;
+    }
+
+    /// Removes the session from the store and expires its cookie, such as when a user logs out.
+    pub fn destroy(&self) {
+        let mut state = self.state();
+
+        state.data.clear();
+        state.destroyed = true;
+    }
+}
+
+impl FromRequest for Session {
+    fn from_request(req: &mut ServerRequest) -> std::result::Result<Self, HttpError> {
+        req.session()
+            .cloned()
+            .ok_or_else(|| HttpError::internal_server_error("Sessions are not enabled."))
+    }
+}
+
+impl ServerRequest {
+    /// The session of this request, if the `Sessions` middleware is used.
+    pub fn session(&self) -> Option<&Session> {
+        self.extensions().get::<Session>()
+    }
+}
+
+/// Middleware that gives each client a session, identified by a random id in a cookie and
+/// stored in a `SessionStore`.
+///
+/// A session is only saved, and its cookie only set, once something is inserted. Unknown or
+/// expired ids are replaced with a new id rather than reused. The cookie is `HttpOnly` with
+/// `SameSite=Lax`, and should be made `Secure` when the server is behind HTTPS.
+pub struct Sessions<S: SessionStore> {
+    store: S,
+    cookie_name: String,
+    path: String,
+    ttl: Duration,
+    secure: bool,
+}
+
+impl<S: SessionStore> Sessions<S> {
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            cookie_name: "session".to_owned(),
+            path: "/".to_owned(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            secure: false,
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `cookie_name` is not a valid cookie name.
+    pub fn cookie_name(mut self, cookie_name: &str) -> Self {
+        assert!(
+            Cookie::new(cookie_name, "").is_ok(),
+            "Invalid session cookie name '{}'.",
+            cookie_name.escape_debug()
+        );
+
+        self.cookie_name = cookie_name.to_owned();
+        self
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `path` is not a valid cookie path.
+    pub fn path(mut self, path: &str) -> Self {
+        assert!(
+            Cookie::build(&self.cookie_name, "")
+                .path(path)
+                .build()
+                .is_ok(),
+            "Invalid session cookie path '{}'.",
+            path.escape_debug()
+        );
+
+        self.path = path.to_owned();
+        self
+    }
+
+    /// How long a session lasts after it was last changed. Defaults to a day.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// The session cookie holding `id`, or expiring it when `id` is `None`.
+    fn cookie(&self, id: Option<&str>) -> Cookie {
+        let builder = match id {
+            Some(id) => Cookie::build(&self.cookie_name, id)
+                .max_age(self.ttl)
+                .secure(self.secure)
+                .http_only(true)
+                .same_site(SameSite::Lax),
+            None => Cookie::build(&self.cookie_name, "").expire(),
+        };
+
+        // The name and path are checked when they are set, and ids are hex.
+        builder
+            .path(&self.path)
+            .build()
+            .expect("Session cookie settings are valid.")
+    }
+
+    fn save(&self, session: &Session, mut res: Response) -> Response {
+        let mut state = session.state();
+
+        let cookie = if state.destroyed {
+            let Some(id) = state.id.take() else {
+                return res;
+            };
+
+            self.store.remove(&id);
+
+            self.cookie(None)
+        } else if state.changed || (state.renew && state.id.is_some()) {
+            if state.renew {
+                if let Some(id) = state.id.take() {
+                    self.store.remove(&id);
+                }
+            }
+
+            let id = state
+                .id
+                .get_or_insert_with(|| crypto::hex_encode(&crypto::random_bytes::<32>()))
+                .clone();
+
+            self.store.save(&id, &state.data, self.ttl);
+
+            self.cookie(Some(&id))
+        } else {
+            return res;
+        };
+
+        res.header
+            .header_map_mut()
+            .append(HeaderName::SET_COOKIE, (&cookie).into());
+
+        res
+    }
+}
+
+impl<S: SessionStore> Middleware for Sessions<S> {
+    fn handle(&self, mut req: ServerRequest, next: Next<'_>) -> Response {
+        let loaded = req
+            .cookies()
+            .get(&self.cookie_name)
+            .and_then(|c| Some((c.value().to_owned(), self.store.load(c.value())?)));
+
+        let session = Session::default();
+
+        if let Some((id, data)) = loaded {
+            let mut state = session.state();
+
+            state.id = Some(id);
+            state.data = data;
+        }
+
+        req.extensions_mut().insert(session.clone());
+
+        let res = next.run(req);
+
+        self.save(&session, res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, time::Duration};
+
+    use crate::{
+        header_item::HeaderItem,
+        middleware::{Middleware, Next},
+        request::{RequestBuilder, ServerRequest},
+        response::{Response, ResponseBuilder},
+        route::RouteKey,
+    };
+
+    use super::{MemoryStore, Session, SessionStore, Sessions};
+
+    fn send(sessions: &Sessions<MemoryStore>, uri: &str, cookie: Option<&str>) -> Response {
+        let mut req = RequestBuilder::new().uri(uri);
+
+        if let Some(cookie) = cookie {
+            req = req.insert_header_key_val("Cookie", cookie).unwrap();
+        }
+
+        let endpoint = |req: ServerRequest| -> Response {
+            let session = req.session().unwrap();
+
+            match req.request.header.path() {
+                "/login" => {
+                    session.renew();
+                    session.insert("user", "zak");
+                }
+                "/visit" => {
+                    session.insert("visits", session.get_as::<u32>("visits").unwrap_or(0) + 1)
+                }
+                "/logout" => session.destroy(),
+                _ => {}
+            }
+
+            ResponseBuilder::new()
+                .body(session.get("user").unwrap_or_default())
+                .build()
+        };
+
+        let middleware: Vec<Box<dyn Middleware>> = Vec::new();
+
+        let req = ServerRequest::new(
+            RouteKey("/".to_owned()),
+            req.build(),
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+        );
+
+        sessions.handle(req, Next::new(&middleware, &endpoint))
+    }
+
+    fn set_cookie(res: &Response) -> Option<&str> {
+        res.header.header_map().get_by_str_key("set-cookie")
+    }
+
+    #[test]
+    fn session_lifecycle() {
+        let sessions = Sessions::new(MemoryStore::new()).secure(true);
+
+        let res = send(&sessions, "/", None);
+        assert_eq!(set_cookie(&res), None);
+        assert!(sessions.store().is_empty());
+
+        let res = send(&sessions, "/login", Some("session=planted"));
+        let cookie = set_cookie(&res).unwrap().to_owned();
+        let id = cookie.split(';').next().unwrap().to_owned();
+
+        assert_eq!(id.len(), "session=".len() + 64);
+        assert!(cookie.contains("HttpOnly") && cookie.contains("Secure"));
+        assert!(cookie.contains("SameSite=Lax") && cookie.contains("Max-Age=86400"));
+
+        let res = send(&sessions, "/", Some(&id));
+        assert_eq!(res.body.contents, b"zak");
+        assert_eq!(set_cookie(&res), None);
+
+        send(&sessions, "/visit", Some(&id));
+        let res = send(&sessions, "/visit", Some(&id));
+        assert_eq!(
+            set_cookie(&res).unwrap().split(';').next(),
+            Some(id.as_str())
+        );
+
+        let data = sessions.store().load(&id["session=".len()..]).unwrap();
+        assert_eq!(data.get("visits").map(String::as_str), Some("2"));
+
+        let res = send(&sessions, "/login", Some(&id));
+        assert_ne!(
+            set_cookie(&res).unwrap().split(';').next(),
+            Some(id.as_str())
+        );
+        assert_eq!(send(&sessions, "/", Some(&id)).body.contents, b"");
+
+        let res = send(&sessions, "/logout", Some(&id));
+        assert_eq!(set_cookie(&res), None);
+
+        let id = set_cookie(&send(&sessions, "/login", None))
+            .unwrap()
+            .split(';')
+            .next()
+            .unwrap()
+            .to_owned();
+
+        let res = send(&sessions, "/logout", Some(&id));
+        assert!(set_cookie(&res).unwrap().contains("Max-Age=0"));
+        assert_eq!(sessions.store().len(), 1);
+    }
+
+    #[test]
+    fn memory_store_expires() {
+        let store = MemoryStore::new();
+
+        let session = Session::default();
+        session.insert("a", 1);
+
+        store.save("old", &session.0.lock().unwrap().data, Duration::ZERO);
+        assert_eq!(store.load("old"), None);
+
+        store.save(
+            "new",
+            &session.0.lock().unwrap().data,
+            Duration::from_secs(60),
+        );
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.load("new").unwrap().get("a").unwrap(), "1");
+
+        store.remove("new");
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid session cookie name")]
+    fn invalid_cookie_name() {
+        let _ = Sessions::new(MemoryStore::new()).cookie_name("my session");
+    }
+}
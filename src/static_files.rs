@@ -0,0 +1,393 @@
+use std::{
+    fs::File,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    conditional::EntityTag,
+    error::HttpError,
+    handler::Handler,
+    header_name::HeaderName,
+    header_value::HeaderValue,
+    http_date::HttpDate,
+    http_status::HttpStatus,
+    mime::Mime,
+    params::percent_encode_path_segment,
+    range::RangedBody,
+    request::{Request, ServerRequest},
+    response::{HttpResponse, Response, ResponseBuilder},
+};
+
+/// Serves files from a directory.
+///
+/// Mount it on a route ending in a catch-all parameter, e.g. `/assets/{*path}`, and the rest of
+/// the request path is resolved relative to the root directory. Paths escaping the root are
+/// rejected with `403 Forbidden`.
+#[derive(Debug, Clone)]
+pub struct StaticFiles {
+    root: PathBuf,
+    index_files: Vec<String>,
+    directory_listing: bool,
+    fallback_file: Option<PathBuf>,
+}
+
+impl StaticFiles {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            index_files: vec!["index.html".to_owned()],
+            directory_listing: false,
+            fallback_file: None,
+        }
+    }
+
+    /// Files to look for, in order, when a directory is requested. Defaults to `index.html`.
+    pub fn index_files<I, S>(mut self, index_files: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.index_files = index_files.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Lists the contents of directories that have no index file.
+    pub fn directory_listing(mut self, enabled: bool) -> Self {
+        self.directory_listing = enabled;
+        self
+    }
+
+    /// A file, relative to the root, served instead of `404 Not Found`. Useful for single page
+    /// applications that route on the client.
+    pub fn fallback_file<P: Into<PathBuf>>(mut self, fallback_file: P) -> Self {
+        self.fallback_file = Some(fallback_file.into());
+        self
+    }
+
+    pub fn serve(&self, req: &ServerRequest) -> std::result::Result<Response, HttpError> {
+        let relative = req.path_params().pop().map(|(_, v)| v).unwrap_or_default();
+
+        let root = self
+            .root
+            .canonicalize()
+            .map_err(|_| HttpError::not_found("Not Found"))?;
+
+        let path = match self.resolve(&root, &relative)? {
+            Some(path) => path,
+            None => return self.not_found(&root, req),
+        };
+
+        if path.is_dir() {
+            let uri_path = req.request.header.path();
+
+            if !uri_path.ends_with('/') {
+                let location = match req.request.header.query() {
+                    Some(q) => format!("{}/?{}", uri_path, q),
+                    None => format!("{}/", uri_path),
+                };
+
+                let location = HeaderValue::try_from(location)
+                    .map_err(|_| HttpError::bad_request("Invalid path."))?;
+
+                return Ok(ResponseBuilder::new()
+                    .status(HttpStatus::MovedPermanently)
+                    .header(HeaderName::LOCATION, location)
+                    .build());
+            }
+
+            if let Some(index) = self
+                .index_files
+                .iter()
+                .map(|i| path.join(i))
+                .find(|i| i.is_file())
+            {
+                return file_response(&index, &req.request);
+            }
+
+            if self.directory_listing {
+                return listing_response(&path, uri_path);
+            }
+
+            return self.not_found(&root, req);
+        }
+
+        file_response(&path, &req.request)
+    }
+
+    /// Maps the decoded request path onto the root, returning `None` if nothing exists there.
+    fn resolve(
+        &self,
+        root: &Path,
+        relative: &str,
+    ) -> std::result::Result<Option<PathBuf>, HttpError> {
+        let forbidden = || HttpError::new("Forbidden", HttpStatus::Forbidden);
+
+        let mut path = root.to_path_buf();
+
+        for segment in relative.split('/').filter(|s| !s.is_empty() && *s != ".") {
+            let mut components = Path::new(segment).components();
+
+            let is_plain_name = matches!(components.next(), Some(Component::Normal(_)))
+                && components.next().is_none()
+                && !segment.contains(['\\', '\0']);
+
+            if !is_plain_name {
+                return Err(forbidden());
+            }
+
+            path.push(segment);
+        }
+
+        match path.canonicalize() {
+            Ok(canonical) if canonical.starts_with(root) => Ok(Some(canonical)),
+            Ok(_) => Err(forbidden()),
+            Err(_) => Ok(None),
+        }
+    }
+
+    fn not_found(
+        &self,
+        root: &Path,
+        req: &ServerRequest,
+    ) -> std::result::Result<Response, HttpError> {
+        match &self.fallback_file {
+            Some(fallback) if root.join(fallback).is_file() => {
+                file_response(&root.join(fallback), &req.request)
+            }
+            _ => Err(HttpError::not_found("Not Found")),
+        }
+    }
+}
+
+impl Handler<ServerRequest> for StaticFiles {
+    fn call(&self, req: ServerRequest) -> Response {
+        Box::new(self.serve(&req)).into_response()
+    }
+}
+
+fn file_response(path: &Path, req: &Request) -> std::result::Result<Response, HttpError> {
+    let not_found = |_| HttpError::not_found("Not Found");
+
+    let file = File::open(path).map_err(not_found)?;
+    let metadata = file.metadata().map_err(not_found)?;
+
+    let mut body = RangedBody::new(file, Mime::from_path(path)).map_err(not_found)?;
+
+    if let Some(etag) = EntityTag::from_file_metadata(&metadata) {
+        body = body.etag(etag);
+    }
+
+    if let Ok(modified) = metadata.modified() {
+        body = body.last_modified(HttpDate::from(modified));
+    }
+
+    body.respond(req)
+}
+
+fn listing_response(dir: &Path, uri_path: &str) -> std::result::Result<Response, HttpError> {
+    let mut entries = std::fs::read_dir(dir)
+        .map_err(|_| HttpError::not_found("Not Found"))?
+        .filter_map(|e| e.ok())
+        .map(|e| {
+            let mut name = e.file_name().to_string_lossy().into_owned();
+
+            if e.path().is_dir() {
+                name.push('/');
+            }
+
+            name
+        })
+        .collect::<Vec<_>>();
+
+    entries.sort();
+
+    let title = format!("Index of {}", escape_html(uri_path));
+
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>{0}</title></head>\n<body>\n<h1>{0}</h1>\n<ul>\n<li><a href=\"../\">../</a></li>\n",
+        title
+    );
+
+    for name in entries {
+        let href = match name.strip_suffix('/') {
+            Some(dir) => format!("{}/", percent_encode_path_segment(dir)),
+            None => percent_encode_path_segment(&name),
+        };
+
+        html.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            href,
+            escape_html(&name)
+        ));
+    }
+
+    html.push_str("</ul>\n</body>\n</html>\n");
+
+    Ok(ResponseBuilder::html(html).build())
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, path::PathBuf};
+
+    use crate::{
+        handler::Handler,
+        header_item::HeaderItem,
+        request::{RequestBuilder, ServerRequest},
+        response::Response,
+        route::RouteKey,
+    };
+
+    use super::StaticFiles;
+
+    fn site_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("http_lib2-static-{}-{}", name, std::process::id()));
+
+        let _ = std::fs::remove_dir_all(&dir);
+
+        std::fs::create_dir_all(dir.join("public/docs")).unwrap();
+        std::fs::write(dir.join("public/index.html"), "<h1>Home</h1>").unwrap();
+        std::fs::write(dir.join("public/app.js"), "console.log(1);").unwrap();
+        std::fs::write(dir.join("public/docs/a b.txt"), "A B").unwrap();
+        std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+
+        dir
+    }
+
+    fn get(files: &StaticFiles, uri: &str) -> Response {
+        send(files, RequestBuilder::new().uri(uri))
+    }
+
+    fn get_range(files: &StaticFiles, uri: &str, range: &str) -> Response {
+        send(
+            files,
+            RequestBuilder::new()
+                .uri(uri)
+                .insert_header_key_val("Range", range)
+                .unwrap(),
+        )
+    }
+
+    fn send(files: &StaticFiles, req: RequestBuilder) -> Response {
+        let req = ServerRequest::new(
+            RouteKey("/assets/{*path}".to_owned()),
+            req.build(),
+            SocketAddr::from(([127, 0, 0, 1], 0)),
+        );
+
+        files.call(req)
+    }
+
+    fn header<'a>(res: &'a Response, key: &str) -> Option<&'a str> {
+        res.header.header_map().get_by_str_key(key)
+    }
+
+    #[test]
+    fn serve_files() {
+        let dir = site_dir("serve");
+        let files = StaticFiles::new(dir.join("public"));
+
+        let res = get(&files, "/assets/app.js");
+        assert_eq!(res.header.status_code, 200);
+        assert_eq!(
+            header(&res, "content-type"),
+            Some("text/javascript; charset=utf-8")
+        );
+        assert_eq!(res.body.contents, b"console.log(1);");
+
+        let res = get(&files, "/assets/docs/a%20b.txt");
+        assert_eq!(res.body.contents, b"A B");
+        assert!(header(&res, "etag").is_some_and(|e| e.starts_with('"')));
+        assert!(header(&res, "last-modified").is_some());
+
+        let res = get_range(&files, "/assets/app.js", "bytes=0-6");
+        assert_eq!(res.header.status_code, 206);
+        assert_eq!(header(&res, "content-range"), Some("bytes 0-6/15"));
+        assert_eq!(res.body.contents, b"console");
+
+        // The file tag is strong, so a download can be resumed with it, but not once the file
+        // has changed.
+        let etag = header(&res, "etag").unwrap().to_owned();
+
+        let resume = |etag: &str| {
+            send(
+                &files,
+                RequestBuilder::new()
+                    .uri("/assets/app.js")
+                    .insert_header_key_val("Range", "bytes=7-")
+                    .unwrap()
+                    .insert_header_key_val("If-Range", etag)
+                    .unwrap(),
+            )
+        };
+
+        let res = resume(&etag);
+        assert_eq!(res.header.status_code, 206);
+        assert_eq!(res.body.contents, b".log(1);");
+
+        std::fs::write(dir.join("public/app.js"), "console.log(22);").unwrap();
+
+        let res = resume(&etag);
+        assert_eq!(res.header.status_code, 200);
+        assert_eq!(res.body.contents, b"console.log(22);");
+
+        let res = get(&files, "/assets/");
+        assert_eq!(res.body.contents, b"<h1>Home</h1>");
+
+        let res = get(&files, "/assets/docs?x=1");
+        assert_eq!(res.header.status_code, 301);
+        assert_eq!(header(&res, "location"), Some("/assets/docs/?x=1"));
+
+        assert_eq!(get(&files, "/assets/docs/").header.status_code, 404);
+        assert_eq!(get(&files, "/assets/missing.css").header.status_code, 404);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn prevent_traversal() {
+        let dir = site_dir("traversal");
+        let files = StaticFiles::new(dir.join("public"));
+
+        for uri in [
+            "/assets/../secret.txt",
+            "/assets/%2e%2e/secret.txt",
+            "/assets/docs/..%2F..%2Fsecret.txt",
+            "/assets/..%5Csecret.txt",
+        ] {
+            let res = get(&files, uri);
+
+            assert_eq!(res.header.status_code, 403, "{}", uri);
+            assert_ne!(res.body.contents, b"secret");
+        }
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn listing_and_fallback() {
+        let dir = site_dir("listing");
+        let files = StaticFiles::new(dir.join("public"))
+            .directory_listing(true)
+            .fallback_file("index.html");
+
+        let res = get(&files, "/assets/docs/");
+        let html = String::from_utf8(res.body.contents).unwrap();
+        assert!(html.contains(r#"<a href="a%20b.txt">a b.txt</a>"#));
+
+        let res = get(&files, "/assets/some/client/route");
+        assert_eq!(res.header.status_code, 200);
+        assert_eq!(res.body.contents, b"<h1>Home</h1>");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,562 @@
+use std::time::Duration;
+
+use crate::{
+    conditional::EntityTag, error::HttpInternalError, header_name::HeaderName,
+    header_value::HeaderValue, http_date::HttpDate, method::Method, mime::Mime, Result,
+};
+
+/// A header that can be read from and written to a `HeaderMap` as a typed value, with
+/// `HeaderMap::typed_get` and `HeaderMap::typed_insert`.
+pub trait TypedHeader: Sized {
+    const NAME: HeaderName;
+
+    /// Parses every value of the header, in order. `values` is never empty, and `None` means the
+    /// header is invalid.
+    fn decode(values: &[&str]) -> Option<Self>;
+
+    fn encode(&self) -> HeaderValue;
+}
+
+/// The value of a header that may only be sent once.
+fn single<'a>(values: &[&'a str]) -> Option<&'a str> {
+    match values {
+        [value] => Some(value.trim()),
+        _ => None,
+    }
+}
+
+/// The items of a comma separated list header, which may be split across several values.
+fn list<'a>(values: &'a [&'a str]) -> impl Iterator<Item = &'a str> + 'a {
+    values
+        .iter()
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+fn is_token(s: &str) -> bool {
+    s.parse::<HeaderName>().is_ok()
+}
+
+macro_rules! typed_header_from_str {
+    ($name:ident, $header:path, $inner:ty) => {
+        impl TypedHeader for $name {
+            const NAME: HeaderName = $header;
+
+            fn decode(values: &[&str]) -> Option<Self> {
+                single(values)?.parse::<$inner>().ok().map($name)
+            }
+
+            fn encode(&self) -> HeaderValue {
+                HeaderValue::from_trusted(self.0.to_string())
+            }
+        }
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentType(pub Mime);
+
+typed_header_from_str!(ContentType, HeaderName::CONTENT_TYPE, Mime);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContentLength(pub u64);
+
+impl TypedHeader for ContentLength {
+    const NAME: HeaderName = HeaderName::CONTENT_LENGTH;
+
+    /// Repeated values are only accepted if they are all the same.
+    fn decode(values: &[&str]) -> Option<Self> {
+        let len = values.first()?.trim();
+
+        if !len.bytes().all(|b| b.is_ascii_digit()) || values.iter().any(|v| v.trim() != len) {
+            return None;
+        }
+
+        len.parse().ok().map(ContentLength)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        self.0.into()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ETag(pub EntityTag);
+
+impl TypedHeader for ETag {
+    const NAME: HeaderName = HeaderName::ETAG;
+
+    fn decode(values: &[&str]) -> Option<Self> {
+        single(values)?.parse().ok().map(ETag)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        (&self.0).into()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date(pub HttpDate);
+
+typed_header_from_str!(Date, HeaderName::DATE, HttpDate);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LastModified(pub HttpDate);
+
+typed_header_from_str!(LastModified, HeaderName::LAST_MODIFIED, HttpDate);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Expires(pub HttpDate);
+
+typed_header_from_str!(Expires, HeaderName::EXPIRES, HttpDate);
+
+/// Credentials sent with a request, such as `Bearer <token>`. The credentials are redacted from
+/// `Debug` output.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Authorization {
+    scheme: String,
+    credentials: String,
+}
+
+impl Authorization {
+    /// Fails if `scheme` is not a token or `credentials` is not a valid header value.
+    pub fn new(scheme: &str, credentials: &str) -> Result<Self> {
+        let credentials = HeaderValue::try_from(credentials.trim())?;
+
+        if !is_token(scheme) {
+            return Err(HttpInternalError::new(format!(
+                "Invalid authorization scheme '{}'.",
+                scheme.escape_debug()
+            )));
+        }
+
+        Ok(Self {
+            scheme: scheme.to_owned(),
+            credentials: credentials.as_str().to_owned(),
+        })
+    }
+
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Whether the scheme is `scheme`, which is compared case-insensitively.
+    pub fn is_scheme(&self, scheme: &str) -> bool {
+        self.scheme.eq_ignore_ascii_case(scheme)
+    }
+
+    pub fn credentials(&self) -> &str {
+        &self.credentials
+    }
+}
+
+impl std::fmt::Debug for Authorization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Authorization")
+            .field("scheme", &self.scheme)
+            .field("credentials", &"Sensitive")
+            .finish()
+    }
+}
+
+impl TypedHeader for Authorization {
+    const NAME: HeaderName = HeaderName::AUTHORIZATION;
+
+    fn decode(values: &[&str]) -> Option<Self> {
+        let value = single(values)?;
+
+        let (scheme, credentials) = value.split_once(' ').unwrap_or((value, ""));
+
+        Self::new(scheme, credentials).ok()
+    }
+
+    fn encode(&self) -> HeaderValue {
+        let value = if self.credentials.is_empty() {
+            self.scheme.clone()
+        } else {
+            format!("{} {}", self.scheme, self.credentials)
+        };
+
+        HeaderValue::from_trusted(value).sensitive()
+    }
+}
+
+/// The `Cache-Control` directives this crate understands. Other directives are ignored when
+/// parsing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    pub no_cache: bool,
+    pub no_store: bool,
+    pub no_transform: bool,
+    pub must_revalidate: bool,
+    pub public: bool,
+    pub private: bool,
+    pub immutable: bool,
+    pub max_age: Option<Duration>,
+    pub s_maxage: Option<Duration>,
+}
+
+impl TypedHeader for CacheControl {
+    const NAME: HeaderName = HeaderName::CACHE_CONTROL;
+
+    fn decode(values: &[&str]) -> Option<Self> {
+        let mut cache_control = CacheControl::default();
+
+        for directive in list(values) {
+            let (name, arg) = match directive.split_once('=') {
+                Some((name, arg)) => (name.trim(), Some(arg.trim().trim_matches('"'))),
+                None => (directive, None),
+            };
+
+            let seconds = || arg.and_then(|a| a.parse().ok()).map(Duration::from_secs);
+
+            match name.to_ascii_lowercase().as_str() {
+                "no-cache" => cache_control.no_cache = true,
+                "no-store" => cache_control.no_store = true,
+                "no-transform" => cache_control.no_transform = true,
+                "must-revalidate" => cache_control.must_revalidate = true,
+                "public" => cache_control.public = true,
+                "private" => cache_control.private = true,
+                "immutable" => cache_control.immutable = true,
+                "max-age" => cache_control.max_age = seconds(),
+                "s-maxage" => cache_control.s_maxage = seconds(),
+                _ => {}
+            }
+        }
+
+        Some(cache_control)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        let flags = [
+            (self.no_cache, "no-cache"),
+            (self.no_store, "no-store"),
+            (self.no_transform, "no-transform"),
+            (self.must_revalidate, "must-revalidate"),
+            (self.public, "public"),
+            (self.private, "private"),
+            (self.immutable, "immutable"),
+        ];
+
+        let mut directives = flags
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, d)| d.to_string())
+            .collect::<Vec<_>>();
+
+        if let Some(max_age) = self.max_age {
+            directives.push(format!("max-age={}", max_age.as_secs()));
+        }
+
+        if let Some(s_maxage) = self.s_maxage {
+            directives.push(format!("s-maxage={}", s_maxage.as_secs()));
+        }
+
+        HeaderValue::from_trusted(directives.join(", "))
+    }
+}
+
+/// The connection options of a `Connection` header, such as `close`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connection(Vec<String>);
+
+impl Connection {
+    pub fn close() -> Self {
+        Self(vec!["close".to_owned()])
+    }
+
+    pub fn keep_alive() -> Self {
+        Self(vec!["keep-alive".to_owned()])
+    }
+
+    pub fn contains(&self, option: &str) -> bool {
+        self.0.iter().any(|o| o.eq_ignore_ascii_case(option))
+    }
+
+    pub fn is_close(&self) -> bool {
+        self.contains("close")
+    }
+
+    pub fn is_keep_alive(&self) -> bool {
+        self.contains("keep-alive")
+    }
+}
+
+impl TypedHeader for Connection {
+    const NAME: HeaderName = HeaderName::CONNECTION;
+
+    fn decode(values: &[&str]) -> Option<Self> {
+        list(values)
+            .map(|o| is_token(o).then(|| o.to_owned()))
+            .collect::<Option<Vec<_>>>()
+            .map(Connection)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        HeaderValue::from_trusted(self.0.join(", "))
+    }
+}
+
+/// The host and optional port of the target of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Host {
+    host: String,
+    port: Option<u16>,
+}
+
+impl Host {
+    /// Fails if `host` is empty or contains whitespace, control characters, `/` or `@`.
+    pub fn new(host: &str, port: Option<u16>) -> Result<Self> {
+        if host.is_empty()
+            || host
+                .chars()
+                .any(|c| c.is_whitespace() || c.is_control() || c == '/' || c == '@')
+        {
+            return Err(HttpInternalError::new(format!(
+                "Invalid host '{}'.",
+                host.escape_debug()
+            )));
+        }
+
+        Ok(Self {
+            host: host.to_owned(),
+            port,
+        })
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+}
+
+impl TypedHeader for Host {
+    const NAME: HeaderName = HeaderName::HOST;
+
+    fn decode(values: &[&str]) -> Option<Self> {
+        let value = single(values)?;
+
+        // An IPv6 literal is bracketed, so only a colon after the closing bracket starts a port.
+        let port_start = value.rfind(':').filter(|i| !value[*i..].contains(']'));
+
+        match port_start {
+            Some(i) => Self::new(&value[..i], Some(value[i + 1..].parse().ok()?)).ok(),
+            None => Self::new(value, None).ok(),
+        }
+    }
+
+    fn encode(&self) -> HeaderValue {
+        match self.port {
+            Some(port) => HeaderValue::from_trusted(format!("{}:{}", self.host, port)),
+            None => HeaderValue::from_trusted(self.host.clone()),
+        }
+    }
+}
+
+macro_rules! typed_header_value {
+    ($(#[$doc:meta])* $name:ident, $header:path) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, PartialEq, Eq)]
+        pub struct $name(HeaderValue);
+
+        impl $name {
+            /// Fails if `value` is not a valid header value.
+            pub fn new(value: &str) -> Result<Self> {
+                HeaderValue::try_from(value).map($name)
+            }
+
+            pub fn as_str(&self) -> &str {
+                self.0.as_str()
+            }
+        }
+
+        impl TypedHeader for $name {
+            const NAME: HeaderName = $header;
+
+            fn decode(values: &[&str]) -> Option<Self> {
+                Self::new(single(values)?).ok()
+            }
+
+            fn encode(&self) -> HeaderValue {
+                self.0.clone()
+            }
+        }
+    };
+}
+
+typed_header_value!(
+    /// The URI a response redirects to, or of a newly created resource.
+    Location,
+    HeaderName::LOCATION
+);
+
+typed_header_value!(UserAgent, HeaderName::USER_AGENT);
+
+/// The methods a resource supports. Unknown methods are skipped when parsing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Allow(pub Vec<Method>);
+
+impl TypedHeader for Allow {
+    const NAME: HeaderName = HeaderName::ALLOW;
+
+    fn decode(values: &[&str]) -> Option<Self> {
+        Some(Allow(list(values).filter_map(|m| m.parse().ok()).collect()))
+    }
+
+    fn encode(&self) -> HeaderValue {
+        HeaderValue::from_trusted(
+            self.0
+                .iter()
+                .map(|m| m.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+    }
+}
+
+/// The request headers that were used to select a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Vary {
+    /// `*`, meaning the response depends on more than request headers.
+    Any,
+    Headers(Vec<HeaderName>),
+}
+
+impl Vary {
+    /// Whether the response varies on the header `name`, which is always true for `Vary::Any`.
+    pub fn contains(&self, name: &str) -> bool {
+        match self {
+            Vary::Any => true,
+            Vary::Headers(headers) => headers.iter().any(|h| *h == name),
+        }
+    }
+}
+
+impl TypedHeader for Vary {
+    const NAME: HeaderName = HeaderName::VARY;
+
+    fn decode(values: &[&str]) -> Option<Self> {
+        if list(values).any(|v| v == "*") {
+            return Some(Vary::Any);
+        }
+
+        list(values)
+            .map(|v| v.parse().ok())
+            .collect::<Option<Vec<_>>>()
+            .map(Vary::Headers)
+    }
+
+    fn encode(&self) -> HeaderValue {
+        match self {
+            Vary::Any => HeaderValue::from_static("*"),
+            Vary::Headers(headers) => HeaderValue::from_trusted(
+                headers
+                    .iter()
+                    .map(|h| h.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        conditional::EntityTag, header_map::HeaderMap, header_name::HeaderName, method::Method,
+        mime::Mime,
+    };
+
+    use super::{
+        Allow, Authorization, CacheControl, Connection, ContentLength, ContentType, ETag, Host,
+        Location, TypedHeader, Vary,
+    };
+
+    fn decode<H: TypedHeader>(values: &[&str]) -> Option<H> {
+        H::decode(values)
+    }
+
+    #[test]
+    fn decode_headers() {
+        assert_eq!(
+            decode::<ContentType>(&["text/html; charset=utf-8"]),
+            Some(ContentType(Mime::TEXT_HTML_UTF_8))
+        );
+        assert_eq!(
+            decode::<ContentLength>(&["42", " 42"]),
+            Some(ContentLength(42))
+        );
+        assert_eq!(decode::<ContentLength>(&["42", "43"]), None);
+        assert_eq!(decode::<ContentLength>(&["+42"]), None);
+        assert_eq!(
+            decode::<ETag>(&["W/\"v1\""]),
+            Some(ETag(EntityTag::weak("v1")))
+        );
+
+        let auth = decode::<Authorization>(&["Bearer abc.def"]).unwrap();
+        assert!(auth.is_scheme("bearer"));
+        assert_eq!(auth.credentials(), "abc.def");
+        assert!(!format!("{:?}", auth).contains("abc"));
+
+        let cache_control =
+            decode::<CacheControl>(&["public, max-age=\"60\"", "No-Transform, x-ext=1"]).unwrap();
+        assert!(cache_control.public && cache_control.no_transform);
+        assert_eq!(cache_control.max_age, Some(Duration::from_secs(60)));
+
+        assert!(decode::<Connection>(&["Upgrade, Close"])
+            .unwrap()
+            .is_close());
+        assert_eq!(decode::<Connection>(&["close, a b"]), None);
+
+        let host = decode::<Host>(&["[::1]:8080"]).unwrap();
+        assert_eq!((host.host(), host.port()), ("[::1]", Some(8080)));
+        assert_eq!(decode::<Host>(&["[::1]"]).unwrap().port(), None);
+        assert_eq!(decode::<Host>(&["a.com", "b.com"]), None);
+        assert_eq!(decode::<Host>(&["a.com:http"]), None);
+
+        assert_eq!(
+            decode::<Allow>(&["GET, HEAD", "BREW, POST"]),
+            Some(Allow(vec![Method::GET, Method::HEAD, Method::POST]))
+        );
+        assert_eq!(decode::<Vary>(&["Accept, *"]), Some(Vary::Any));
+        assert!(decode::<Vary>(&["Accept", "accept-encoding"])
+            .unwrap()
+            .contains("Accept-Encoding"));
+    }
+
+    #[test]
+    fn typed_get_and_insert() {
+        let mut headers = HeaderMap::new();
+
+        headers.typed_insert(CacheControl {
+            no_store: true,
+            max_age: Some(Duration::ZERO),
+            ..Default::default()
+        });
+        headers.typed_insert(Vary::Headers(vec![HeaderName::ACCEPT, HeaderName::ORIGIN]));
+        headers.typed_insert(Location::new("/users/1").unwrap());
+        headers.typed_insert(Authorization::new("Basic", "YTpi").unwrap());
+
+        assert_eq!(
+            headers.get_by_str_key("cache-control"),
+            Some("no-store, max-age=0")
+        );
+        assert_eq!(headers.get_by_str_key("vary"), Some("Accept, Origin"));
+        assert_eq!(
+            headers.typed_get::<Location>().unwrap().as_str(),
+            "/users/1"
+        );
+        assert_eq!(headers.get_by_str_key("authorization"), Some("Basic YTpi"));
+        assert!(headers.get("authorization").unwrap().is_sensitive());
+        assert_eq!(headers.typed_get::<ContentType>(), None);
+
+        assert!(Location::new("/\r\nSet-Cookie: a=1").is_err());
+        assert!(Authorization::new("Bad Scheme", "x").is_err());
+    }
+}